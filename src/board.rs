@@ -8,11 +8,14 @@ mod pawn;
 mod queen;
 mod rook;
 pub mod square;
+mod static_exchange;
 
 mod errors;
+pub mod generation_mode;
 pub mod piece;
 mod straight_moving_piece;
 mod test_utils;
+mod zobrist;
 
 use crate::board::pawn::PawnMovement;
 use std::convert::TryFrom;
@@ -21,10 +24,12 @@ use std::fmt;
 use piece::*;
 
 use self::bishop::{BishopMovement, BISHOP_OFFSETS};
+pub use self::castling::CastlingDirection;
 use self::castling::{CastlingMovement, CastlingState};
 use self::chess_move::ChessMove;
 use self::errors::*;
 use self::game::ChessResult;
+use self::generation_mode::GenerationMode;
 use self::king::{KingMovement, KING_OFFSETS};
 use self::knight::{KnightMovement, KNIGHT_OFFSETS};
 use self::pawn::PawnState;
@@ -64,6 +69,11 @@ pub struct Board {
     /// black queenside, black kingside, white queenside,
     /// white kingside
     castling_availability: [bool; 4],
+
+    // Zobrist hash of everything above. Every change to the position goes
+    // through a function that updates it, so it never has to be computed
+    // from scratch after construction.
+    hash: u64,
 }
 
 impl fmt::Display for Board {
@@ -92,9 +102,9 @@ impl fmt::Display for Board {
     }
 }
 
-impl Board {
+impl Default for Board {
     // Construct a default board
-    pub fn default() -> Board {
+    fn default() -> Board {
         use {PieceType::*, Side::*};
         let mut white_back_rank = vec![
             Some(Piece::new(White, Rook)),
@@ -133,9 +143,13 @@ impl Board {
             en_passant_target: None,
             current_move: Side::White,
             castling_availability: [true, true, true, true],
+            hash: 0,
         }
+        .with_computed_hash()
     }
+}
 
+impl Board {
     /// Generates a board state from ascii art of the board
     /// Pieces are denoted like FEN notation, just in a grid
     /// instead of compressed. Makes for easier reading of tests etc.
@@ -171,7 +185,65 @@ impl Board {
             // There's no real way to get the castling availability
             // while constructing a board from pieces, so we set all to false
             castling_availability: [false, false, false, false],
+            hash: 0,
+        }
+        .with_computed_hash()
+    }
+
+    // Sets the hash to the hash of the position, computed from scratch
+    fn with_computed_hash(mut self) -> Self {
+        self.hash = 0;
+
+        for (index, piece) in self.squares.iter().enumerate() {
+            if let Some(piece) = piece {
+                self.hash ^= zobrist::piece_key(index, *piece);
+            }
+        }
+
+        if self.current_move == Side::Black {
+            self.hash ^= zobrist::side_key();
+        }
+
+        for (index, &available) in self.castling_availability.iter().enumerate()
+        {
+            if available {
+                self.hash ^= zobrist::castling_key(index);
+            }
+        }
+
+        if let Some(target) = self.en_passant_target {
+            self.hash ^= zobrist::en_passant_key(self.square_index(target));
+        }
+
+        self
+    }
+
+    fn square_index(&self, square: Square) -> usize {
+        (square.rank * self.width + square.file) as usize
+    }
+
+    // Sets the en passant target, keeping the hash up to date
+    fn set_en_passant_target(&mut self, target: Option<Square>) {
+        if let Some(old) = self.en_passant_target {
+            self.hash ^= zobrist::en_passant_key(self.square_index(old));
         }
+        if let Some(new) = target {
+            self.hash ^= zobrist::en_passant_key(self.square_index(new));
+        }
+
+        self.en_passant_target = target;
+    }
+
+    // Sets the castling availability, keeping the hash up to date
+    fn set_castling_availability(&mut self, availability: [bool; 4]) {
+        let changes = self.castling_availability.iter().zip(&availability);
+        for (index, (old, new)) in changes.enumerate() {
+            if old != new {
+                self.hash ^= zobrist::castling_key(index);
+            }
+        }
+
+        self.castling_availability = availability;
     }
 
     /// A read only view into the squares of board.
@@ -179,6 +251,11 @@ impl Board {
         &self.squares
     }
 
+    /// How many files the board has
+    pub fn get_width(&self) -> u8 {
+        self.width
+    }
+
     /// A view into the currently moving side
     ///
     /// This doesn't need to return a reference, because a Side is just a binary
@@ -189,27 +266,80 @@ impl Board {
 
     pub fn flip_current_side(&mut self) -> &mut Self {
         self.current_move = self.current_move.flip();
+        self.hash ^= zobrist::side_key();
         self
     }
 
+    /// A hash of the position, including the side to move and castling and en
+    /// passant state. Useful as a key for caching things about positions.
+    ///
+    /// This is kept up to date as moves are made, so it's free to call.
+    pub fn get_hash(&self) -> u64 {
+        self.hash
+    }
+
     // Generates a list of moves that are possible from the
     // current board state.
     pub fn generate_moves(
         &self,
         checked: bool,
     ) -> Result<Vec<ChessMove>, &'static str> {
+        self.generate_moves_with_mode(checked, GenerationMode::All)
+    }
+
+    /// Generates the subset of possible moves from the current board state
+    /// that `mode` selects
+    pub fn generate_moves_with_mode(
+        &self,
+        checked: bool,
+        mode: GenerationMode,
+    ) -> Result<Vec<ChessMove>, &'static str> {
+        if mode == GenerationMode::Evasions && !self.check_king_threat()? {
+            return Ok(vec![]);
+        }
+
         let mut moves = Vec::with_capacity(224);
-        moves.append(&mut self.generate_pawn_moves(checked)?);
-        moves.append(&mut self.generate_knight_moves(checked)?);
-        moves.append(&mut self.generate_bishop_moves(checked)?);
-        moves.append(&mut self.generate_rook_moves(checked)?);
-        moves.append(&mut self.generate_queen_moves(checked)?);
-        moves.append(&mut self.generate_king_moves(checked)?);
-        moves.append(&mut self.generate_castling_moves(checked)?);
+        moves.append(&mut self.generate_pawn_moves(checked, mode)?);
+        moves.append(&mut self.generate_knight_moves(checked, mode)?);
+        moves.append(&mut self.generate_bishop_moves(checked, mode)?);
+        moves.append(&mut self.generate_rook_moves(checked, mode)?);
+        moves.append(&mut self.generate_queen_moves(checked, mode)?);
+        moves.append(&mut self.generate_king_moves(checked, mode)?);
+        moves.append(&mut self.generate_castling_moves(checked, mode)?);
 
         Ok(moves)
     }
 
+    /// Returns the piece that `chess_move` would capture, if any
+    pub fn get_captured_piece(&self, chess_move: &ChessMove) -> Option<Piece> {
+        match *chess_move {
+            ChessMove::SimpleMove(_, to) => self
+                .get_piece_at_position(to)
+                .ok()
+                .flatten()
+                .filter(|p| p.side != self.current_move),
+            ChessMove::EnPassant(_, _, capturing) => {
+                self.get_piece_at_position(capturing).ok().flatten()
+            }
+            ChessMove::Castling(_) | ChessMove::NullMove => None,
+        }
+    }
+
+    /// Returns the piece that's moved by `chess_move`. For castling, that's
+    /// the king.
+    pub fn get_moving_piece(&self, chess_move: &ChessMove) -> Option<Piece> {
+        match *chess_move {
+            ChessMove::SimpleMove(from, _)
+            | ChessMove::EnPassant(from, _, _) => {
+                self.get_piece_at_position(from).ok().flatten()
+            }
+            ChessMove::Castling(_) => {
+                Some(Piece::new(self.current_move, PieceType::King))
+            }
+            ChessMove::NullMove => None,
+        }
+    }
+
     // Generates a list of future board states that are possible from the
     // current board state.
     pub fn generate_moved_boards(
@@ -247,7 +377,16 @@ impl Board {
         piece: Option<Piece>,
         square: Square,
     ) -> Result<(), &'static str> {
-        self.squares[(square.rank * self.width + square.file) as usize] = piece;
+        let index = self.square_index(square);
+
+        if let Some(old) = self.squares[index] {
+            self.hash ^= zobrist::piece_key(index, old);
+        }
+        if let Some(new) = piece {
+            self.hash ^= zobrist::piece_key(index, new);
+        }
+
+        self.squares[index] = piece;
         Ok(())
     }
 
@@ -270,6 +409,26 @@ impl Board {
         Ok(new_board)
     }
 
+    // Checks that `chess_move` can be made, and that it fits `mode`. Used by
+    // move generation, which returns the move if so.
+    fn validate_generated_move(
+        &self,
+        chess_move: ChessMove,
+        checked: bool,
+        mode: GenerationMode,
+    ) -> Option<ChessMove> {
+        let mut new_board = self.clone();
+        new_board.make_move(chess_move.clone(), checked).ok()?;
+
+        // After the move, it's the opponent's turn, so this checks if their
+        // king is threatened
+        if mode.requires_check() && !new_board.check_king_threat().ok()? {
+            return None;
+        }
+
+        Some(chess_move)
+    }
+
     pub fn check_king_threat(&self) -> Result<bool, &'static str> {
         let opponents_side = self.current_move.flip();
 
@@ -411,6 +570,23 @@ impl Board {
         }
     }
 
+    /// Whether neither side has enough material left to ever checkmate, which
+    /// is only the case with bare kings, or a single knight or bishop besides
+    /// them. `get_game_result` doesn't check this, since it's usually better
+    /// handled by whatever is playing the game.
+    pub fn has_insufficient_material(&self) -> bool {
+        let mut minor_pieces = 0;
+        for piece in self.squares.iter().flatten() {
+            match piece.piece_type {
+                PieceType::King => {}
+                PieceType::Knight | PieceType::Bishop => minor_pieces += 1,
+                _ => return false,
+            }
+        }
+
+        minor_pieces <= 1
+    }
+
     // Executes the given `chess_move` in place on self
     //
     // Returns error if the move can't be performed
@@ -427,9 +603,10 @@ impl Board {
                 self.capture_en_passant(from, to, capturing)?
             }
             ChessMove::Castling(dir) => self.castle(dir, checked)?,
-            ChessMove::NullMove => {
-                self.flip_current_side();
-            }
+
+            // Passing doesn't move anything, the side to move is flipped
+            // below like for every other move
+            ChessMove::NullMove => {}
         };
 
         if checked && self.check_king_threat()? {
@@ -439,7 +616,7 @@ impl Board {
         self.update_en_passant_target(&chess_move)?;
         self.update_castling_state(&chess_move);
 
-        self.current_move = self.current_move.flip();
+        self.flip_current_side();
 
         Ok(())
     }
@@ -592,6 +769,8 @@ impl Board {
 
 #[cfg(test)]
 mod test {
+    use std::convert::TryInto;
+
     use super::*;
 
     mod default {
@@ -776,14 +955,224 @@ mod test {
                     let mut new_board = board.clone();
                     new_board.make_move(m.clone(), false).unwrap();
 
-                    let count = generate_move_tree(&new_board, depth - 1);
-
-                    count
+                    generate_move_tree(&new_board, depth - 1)
                 })
                 .sum()
         }
     }
 
+    /// Checks that, throughout the move tree, the generation modes split up
+    /// the moves the way they say they do
+    fn check_generation_modes(board: &Board, depth: u8) {
+        use GenerationMode::*;
+
+        let generate =
+            |mode| board.generate_moves_with_mode(true, mode).unwrap();
+        let all = generate(All);
+        let captures = generate(Captures);
+        let quiets = generate(Quiets);
+
+        assert_eq!(all.len(), captures.len() + quiets.len());
+        for m in &all {
+            assert!(captures.contains(m) != quiets.contains(m));
+        }
+
+        for m in generate(Checks) {
+            assert!(quiets.contains(&m));
+
+            let mut moved_board = board.clone();
+            moved_board.make_move(m, true).unwrap();
+            assert!(moved_board.check_king_threat().unwrap());
+        }
+
+        if board.check_king_threat().unwrap() {
+            assert_eq!(generate(Evasions), all);
+        } else {
+            assert!(generate(Evasions).is_empty());
+        }
+
+        if depth > 0 {
+            for m in all {
+                let mut moved_board = board.clone();
+                moved_board.make_move(m, false).unwrap();
+                check_generation_modes(&moved_board, depth - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn generation_modes_add_up_from_default() {
+        check_generation_modes(&Board::default(), 2);
+    }
+
+    #[test]
+    fn generation_modes_add_up_with_castling_and_en_passant() {
+        let mut board = Board::from_art(
+            "r...k..r\n\
+             ppp..ppp\n\
+             ..n..q..\n\
+             ...pP...\n\
+             .b......\n\
+             ..N..N..\n\
+             PPP..PPP\n\
+             R...K..R\n",
+        )
+        .unwrap();
+        board.set_castling_availability([true, true, true, true]);
+        board.set_en_passant_target(Some(Square { rank: 5, file: 3 }));
+
+        check_generation_modes(&board, 1);
+    }
+
+    #[test]
+    fn generates_only_captures() {
+        let board = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ....p...\n\
+             ...p....\n\
+             ...Q....\n\
+             ........\n\
+             ........\n\
+             ....K...\n",
+        )
+        .unwrap();
+
+        let captures = board
+            .generate_moves_with_mode(true, GenerationMode::Captures)
+            .unwrap();
+
+        assert_eq!(
+            captures,
+            vec![ChessMove::SimpleMove(
+                Square { rank: 3, file: 3 },
+                Square { rank: 4, file: 3 }
+            )]
+        );
+    }
+
+    #[test]
+    fn null_move_passes_the_turn() {
+        let mut board = Board::default();
+        board.make_move("e2e4".try_into().unwrap(), true).unwrap();
+        assert!(board.en_passant_target.is_some());
+
+        board.make_move(ChessMove::NullMove, true).unwrap();
+
+        assert_eq!(board.get_current_side(), Side::White);
+        assert_eq!(board.en_passant_target, None);
+    }
+
+    #[test]
+    fn cant_pass_while_in_check() {
+        let mut board = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             r...K...\n",
+        )
+        .unwrap();
+
+        assert!(board.make_move(ChessMove::NullMove, true).is_err());
+    }
+
+    #[test]
+    fn hash_is_kept_up_to_date() {
+        let mut board = Board::default();
+
+        for ply in 0..60 {
+            let moves = board.generate_moves(true).unwrap();
+            if moves.is_empty() {
+                break;
+            }
+
+            board
+                .make_move(moves[ply * 7 % moves.len()].clone(), true)
+                .unwrap();
+            assert_eq!(
+                board.get_hash(),
+                board.clone().with_computed_hash().get_hash()
+            );
+        }
+    }
+
+    #[test]
+    fn transpositions_have_the_same_hash() {
+        let mut board = Board::default();
+        let knight_moves = [
+            ((0, 6), (2, 5)),
+            ((7, 6), (5, 5)),
+            ((2, 5), (0, 6)),
+            ((5, 5), (7, 6)),
+        ];
+
+        for ((from_rank, from_file), (to_rank, to_file)) in knight_moves {
+            let from = Square {
+                rank: from_rank,
+                file: from_file,
+            };
+            let to = Square {
+                rank: to_rank,
+                file: to_file,
+            };
+            board
+                .make_move(ChessMove::SimpleMove(from, to), true)
+                .unwrap();
+        }
+
+        assert_eq!(board.get_hash(), Board::default().get_hash());
+
+        board.flip_current_side();
+        assert_ne!(board.get_hash(), Board::default().get_hash());
+    }
+
+    #[test]
+    fn detects_insufficient_material() {
+        let lone_knight = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ...N....\n\
+             ....K...\n",
+        )
+        .unwrap();
+        assert!(lone_knight.has_insufficient_material());
+
+        let two_knights = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ...NN...\n\
+             ....K...\n",
+        )
+        .unwrap();
+        assert!(!two_knights.has_insufficient_material());
+
+        let pawn = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ...P....\n\
+             ....K...\n",
+        )
+        .unwrap();
+        assert!(!pawn.has_insufficient_material());
+        assert!(!Board::default().has_insufficient_material());
+    }
+
     #[test]
     fn from_art_works_as_expected() {
         let art = "rnbqkbnr\n\
@@ -801,7 +1190,7 @@ mod test {
         // has castling as true, but art returns it as false
         // So just to make testing equality easier, set
         // it to true here too
-        board.castling_availability = [true, true, true, true];
+        board.set_castling_availability([true, true, true, true]);
 
         assert_eq!(board, Board::default());
     }
@@ -1,14 +1,15 @@
 use crate::board::Board;
 
 use super::{
-    chess_move::ChessMove, straight_moving_piece::StraightMovingPieceMovement,
-    Offset, PieceType,
+    chess_move::ChessMove, generation_mode::GenerationMode,
+    straight_moving_piece::StraightMovingPieceMovement, Offset, PieceType,
 };
 
 pub trait BishopMovement: StraightMovingPieceMovement {
     fn generate_bishop_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str>;
 }
 
@@ -23,11 +24,13 @@ impl BishopMovement for Board {
     fn generate_bishop_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str> {
         self.generate_straight_moves(
             &BISHOP_OFFSETS,
             PieceType::Bishop,
             checked,
+            mode,
         )
     }
 }
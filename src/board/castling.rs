@@ -5,7 +5,7 @@ use self::implementation::{
     CastlingStateImpl,
 };
 
-use super::{chess_move::ChessMove, Board};
+use super::{chess_move::ChessMove, generation_mode::GenerationMode, Board};
 
 /// Define which side castlign is going to happen
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -30,9 +30,13 @@ pub trait CastlingMovement {
     /// If `checked` is true, only returns moves where the king wouldn't be
     /// in danger. If false, returns moves where king might be in danger as
     /// well
+    ///
+    /// Castling never captures anything, so nothing is returned if `mode`
+    /// doesn't include quiet moves
     fn generate_castling_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str>;
 
     /// Changes `self` in place by castling in the given `dir`
@@ -151,9 +155,10 @@ impl CastlingMovement for Board {
     fn generate_castling_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str> {
         let any_castling_state_enabled = self.is_any_castling_state_enabled();
-        if !any_castling_state_enabled {
+        if !any_castling_state_enabled || !mode.includes_quiets() {
             return Ok(vec![]);
         }
 
@@ -170,6 +175,18 @@ impl CastlingMovement for Board {
                 continue;
             }
 
+            if mode.requires_check()
+                && self
+                    .validate_generated_move(
+                        ChessMove::Castling(dir),
+                        checked,
+                        mode,
+                    )
+                    .is_none()
+            {
+                continue;
+            }
+
             moves.push(ChessMove::Castling(dir));
         }
 
@@ -81,7 +81,9 @@ impl CastlingStateImpl for Board {
     }
     fn disable_castling(&mut self, side: Side, direction: CastlingDirection) {
         let index = calculate_index(side, direction);
-        self.castling_availability[index] = false;
+        let mut availability = self.castling_availability;
+        availability[index] = false;
+        self.set_castling_availability(availability);
     }
 }
 
@@ -344,7 +346,7 @@ mod test {
             #[test]
             fn returns_false_when_castling_disallowed() {
                 let mut board = Board::default();
-                board.castling_availability = [true, false, true, false];
+                board.set_castling_availability([true, false, true, false]);
 
                 assert!(!board.get_castling_state(
                     Side::Black,
@@ -548,20 +550,18 @@ mod test {
 
             #[test]
             fn works_with_one_true() {
-                let mut board = Board::default();
-
                 // Set white castling queenside to be allowed
-                board.castling_availability = [false, false, true, false];
+                let mut board = Board::default();
+                board.set_castling_availability([false, false, true, false]);
 
                 assert!(board.is_any_castling_state_enabled());
             }
 
             #[test]
             fn works_with_none_true() {
-                let mut board = Board::default();
-
                 // Black is allowed to castle, white isn't
-                board.castling_availability = [true, true, false, false];
+                let mut board = Board::default();
+                board.set_castling_availability([true, true, false, false]);
 
                 assert!(!board.is_any_castling_state_enabled());
             }
@@ -581,7 +581,7 @@ mod test {
             )
             .unwrap();
 
-            board.castling_availability = [false, false, true, true];
+            board.set_castling_availability([false, false, true, true]);
 
             board
         }
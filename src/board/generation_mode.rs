@@ -0,0 +1,40 @@
+/// Selects which subset of the legal moves move generation produces
+///
+/// `Captures` and `Quiets` split `All` into two halves that don't overlap, so
+/// that searches can look at the moves that matter most without generating
+/// everything and filtering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GenerationMode {
+    /// Every move
+    All,
+
+    /// Moves that capture a piece, including en passant captures. The move
+    /// generator doesn't produce promotions yet, but they would belong here.
+    Captures,
+
+    /// Moves that don't capture anything, including castling
+    Quiets,
+
+    /// Every move, if the side to move is in check. Nothing otherwise.
+    Evasions,
+
+    /// Moves that don't capture anything, but put the opponent in check
+    Checks,
+}
+
+impl GenerationMode {
+    /// Whether moves that capture a piece can be generated in this mode
+    pub fn includes_captures(self) -> bool {
+        matches!(self, Self::All | Self::Captures | Self::Evasions)
+    }
+
+    /// Whether moves that don't capture anything can be generated in this mode
+    pub fn includes_quiets(self) -> bool {
+        !matches!(self, Self::Captures)
+    }
+
+    /// Whether a move has to give check to be generated in this mode
+    pub fn requires_check(self) -> bool {
+        self == Self::Checks
+    }
+}
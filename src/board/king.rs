@@ -1,11 +1,15 @@
 use crate::board::Board;
 
-use super::{chess_move::ChessMove, Offset, Piece, PieceType::King};
+use super::{
+    chess_move::ChessMove, generation_mode::GenerationMode, Offset, Piece,
+    PieceType::King,
+};
 
 pub trait KingMovement {
     fn generate_king_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str>;
 }
 
@@ -24,6 +28,7 @@ impl KingMovement for Board {
     fn generate_king_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str> {
         let positions = self.get_positions_of_matching_pieces(Piece::new(
             self.current_move,
@@ -40,16 +45,21 @@ impl KingMovement for Board {
                     })
                     .map(move |new| (pos, new))
             })
+            .filter(|(_, new)| match self.get_piece_at_position(*new) {
+                Ok(None) => mode.includes_quiets(),
+                Ok(Some(_)) => mode.includes_captures(),
+                Err(_) => false,
+            })
             .filter_map(|(old, new)| {
                 // TODO: for now, we keep attempting to create a board
                 // temporarily, to use make_move as validation. This really
                 // should be removed, and we should just have a "validate_move"
                 // method
-                self.new_board_with_moved_piece(old, new, checked).ok()?;
-
-                // If the last statement didn't short circuit and exit with an
-                // error, we know that the move is valid
-                ChessMove::SimpleMove(old, new).into()
+                self.validate_generated_move(
+                    ChessMove::SimpleMove(old, new),
+                    checked,
+                    mode,
+                )
             })
             .collect();
 
@@ -1,11 +1,15 @@
 use crate::board::Board;
 
-use super::{chess_move::ChessMove, Offset, Piece, PieceType::*};
+use super::{
+    chess_move::ChessMove, generation_mode::GenerationMode, Offset, Piece,
+    PieceType::*,
+};
 
 pub trait KnightMovement {
     fn generate_knight_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str>;
 }
 
@@ -24,6 +28,7 @@ impl KnightMovement for Board {
     fn generate_knight_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str> {
         let knight_positions = self.get_positions_of_matching_pieces(
             Piece::new(self.current_move, Knight),
@@ -37,14 +42,15 @@ impl KnightMovement for Board {
                 .filter_map(|dir| {
                     self.add_offset_to_position(old_pos, *dir).ok()
                 })
-                // Check target square: can't take own pieces
+                // Check target square: can't take own pieces, and should
+                // only capture or only move quietly if the mode says so
                 .filter(|new_pos| {
                     match self.get_piece_at_position(*new_pos).unwrap() {
-                        None => true,
+                        None => mode.includes_quiets(),
                         Some(Piece { side: os, .. })
                             if os != self.current_move =>
                         {
-                            true
+                            mode.includes_captures()
                         }
                         _ => false,
                     }
@@ -55,12 +61,11 @@ impl KnightMovement for Board {
                     // temporarily, to use make_move as validation. This really
                     // should be removed, and we should just have a
                     // "validate_move" method
-                    self.new_board_with_moved_piece(old_pos, new_pos, checked)
-                        .ok()?;
-
-                    // If the last statement didn't short circuit and exit with
-                    // an error, we know that the move is valid
-                    ChessMove::SimpleMove(old_pos, new_pos).into()
+                    self.validate_generated_move(
+                        ChessMove::SimpleMove(old_pos, new_pos),
+                        checked,
+                        mode,
+                    )
                 });
 
             possible_moves.extend(new_moves);
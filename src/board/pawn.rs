@@ -2,6 +2,7 @@ use crate::board::Board;
 
 use super::{
     chess_move::ChessMove,
+    generation_mode::GenerationMode,
     Offset, Piece,
     PieceType::{self, *},
     Side::*,
@@ -21,6 +22,7 @@ pub trait PawnMovement {
     fn generate_pawn_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str>;
 
     /// Takes a deconstructed ChessMove::EnPassant, and executes it on self.
@@ -41,7 +43,7 @@ impl PawnState for Board {
             ChessMove::Castling(_)
             | ChessMove::EnPassant(_, _, _)
             | ChessMove::NullMove => {
-                self.en_passant_target = None;
+                self.set_en_passant_target(None);
                 Ok(())
             }
             ChessMove::SimpleMove(from, to) => {
@@ -59,13 +61,11 @@ impl PawnState for Board {
                         file: 0,
                     };
 
-                    self.en_passant_target =
-                        Some(self.add_offset_to_position(
-                            from,
-                            en_passent_target_dir,
-                        )?);
+                    let target = self
+                        .add_offset_to_position(from, en_passent_target_dir)?;
+                    self.set_en_passant_target(Some(target));
                 } else {
-                    self.en_passant_target = None;
+                    self.set_en_passant_target(None);
                 }
 
                 Ok(())
@@ -78,6 +78,7 @@ impl PawnMovement for Board {
     fn generate_pawn_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str> {
         let mut possible_moves = vec![];
         let pawn_positions = self.get_positions_of_matching_pieces(
@@ -126,14 +127,15 @@ impl PawnMovement for Board {
                 // temporarily, to use make_move as validation. This really
                 // should be removed, and we should just have a "validate_move"
                 // method
-                self.new_board_with_moved_piece(*old_pos, new_pos, checked)
-                    .ok()?;
-
-                // If the last statement didn't short circuit and exit with an
-                // error, we know that the move is valid
-                ChessMove::SimpleMove(*old_pos, new_pos).into()
+                self.validate_generated_move(
+                    ChessMove::SimpleMove(*old_pos, new_pos),
+                    checked,
+                    mode,
+                )
             });
-        possible_moves.extend(single_square_pawn_moves);
+        if mode.includes_quiets() {
+            possible_moves.extend(single_square_pawn_moves);
+        }
 
         // Append double square pawn moves
         let double_square_pawn_moves = pawn_positions
@@ -182,15 +184,15 @@ impl PawnMovement for Board {
                 // temporarily, to use make_move as validation. This really
                 // should be removed, and we should just have a "validate_move"
                 // method
-                self.new_board_with_moved_piece(*old_pos, new_pos, checked)
-                    .ok()?;
-
-                // If the last statement didn't short circuit and exit with an
-                // error, we know that the move is valid
-                ChessMove::SimpleMove(*old_pos, new_pos).into()
+                self.validate_generated_move(
+                    ChessMove::SimpleMove(*old_pos, new_pos),
+                    checked,
+                    mode,
+                )
             });
-
-        possible_moves.extend(double_square_pawn_moves);
+        if mode.includes_quiets() {
+            possible_moves.extend(double_square_pawn_moves);
+        }
 
         // Append pawn captures
         let pawn_capture_left_moves = pawn_positions
@@ -248,7 +250,9 @@ impl PawnMovement for Board {
                 // error, we know that the move is valid
                 en_passant_move.into()
             });
-        possible_moves.extend(pawn_capture_moves);
+        if mode.includes_captures() {
+            possible_moves.extend(pawn_capture_moves);
+        }
 
         // Append en passant captures
         let en_passant_moves = pawn_capture_left_moves
@@ -278,7 +282,9 @@ impl PawnMovement for Board {
                 // error, we know that the move is valid
                 ChessMove::EnPassant(*old_pos, new_pos, capturing).into()
             });
-        possible_moves.extend(en_passant_moves);
+        if mode.includes_captures() {
+            possible_moves.extend(en_passant_moves);
+        }
 
         Ok(possible_moves)
     }
@@ -314,13 +320,8 @@ mod test {
     // .....
     // Where o is the en passant target
     fn get_test_board_for_pawn_captures() -> Board {
-        let mut board = Board {
-            squares: vec![None; 5 * 5],
-            width: 5,
-            en_passant_target: Some(Square { rank: 2, file: 3 }),
-            current_move: White,
-            castling_availability: [false, false, false, false],
-        };
+        let mut board = Board::with_pieces(vec![None; 5 * 5], 5);
+        board.set_en_passant_target(Some(Square { rank: 2, file: 3 }));
 
         board
             .set_piece_at_position(
@@ -380,13 +381,7 @@ mod test {
 
         squares[33] = Some(Piece::new(Black, Pawn));
 
-        Board {
-            squares,
-            width: 7,
-            en_passant_target: None,
-            current_move: White,
-            castling_availability: [false, false, false, false],
-        }
+        Board::with_pieces(squares, 7)
     }
 
     #[test]
@@ -501,7 +496,7 @@ mod test {
         )
         .unwrap();
 
-        board.current_move = Black;
+        board.flip_current_side();
 
         // Push pawn to create en_passant_target
         board
@@ -1,14 +1,15 @@
 use crate::board::Board;
 
 use super::{
-    chess_move::ChessMove, straight_moving_piece::StraightMovingPieceMovement,
-    Offset, PieceType,
+    chess_move::ChessMove, generation_mode::GenerationMode,
+    straight_moving_piece::StraightMovingPieceMovement, Offset, PieceType,
 };
 
 pub trait QueenMovement: StraightMovingPieceMovement {
     fn generate_queen_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str>;
 }
 
@@ -16,6 +17,7 @@ impl QueenMovement for Board {
     fn generate_queen_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str> {
         let offsets: Vec<Offset> = [
             (0, 1),
@@ -31,7 +33,7 @@ impl QueenMovement for Board {
         .map(|(x, y)| Offset { rank: *y, file: *x })
         .collect();
 
-        self.generate_straight_moves(&offsets, PieceType::Queen, checked)
+        self.generate_straight_moves(&offsets, PieceType::Queen, checked, mode)
     }
 }
 
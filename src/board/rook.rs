@@ -1,14 +1,15 @@
 use crate::board::Board;
 
 use super::{
-    chess_move::ChessMove, straight_moving_piece::StraightMovingPieceMovement,
-    Offset, PieceType,
+    chess_move::ChessMove, generation_mode::GenerationMode,
+    straight_moving_piece::StraightMovingPieceMovement, Offset, PieceType,
 };
 
 pub trait RookMovement: StraightMovingPieceMovement {
     fn generate_rook_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str>;
 }
 
@@ -23,8 +24,14 @@ impl RookMovement for Board {
     fn generate_rook_moves(
         &self,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str> {
-        self.generate_straight_moves(&ROOK_OFFSETS, PieceType::Rook, checked)
+        self.generate_straight_moves(
+            &ROOK_OFFSETS,
+            PieceType::Rook,
+            checked,
+            mode,
+        )
     }
 }
 
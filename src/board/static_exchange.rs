@@ -0,0 +1,327 @@
+use crate::evaluation::{evaluation_result::Centipawns, material::PieceValues};
+
+use super::{
+    bishop::BISHOP_OFFSETS, chess_move::ChessMove, king::KING_OFFSETS,
+    knight::KNIGHT_OFFSETS, rook::ROOK_OFFSETS, Board, Offset, Piece,
+    PieceType, Side, Square,
+};
+
+impl Board {
+    /// Static exchange evaluation: how much material the side to move wins
+    /// (or loses, if negative) by making `chess_move` and then trading off
+    /// pieces on the target square, with both sides recapturing with their
+    /// least valuable piece for as long as it's worth it.
+    ///
+    /// Pieces attacking through other attackers, like a queen behind a rook,
+    /// join in once the pieces in front of them have been traded off. Pins
+    /// aren't taken into account.
+    ///
+    /// Uses the default piece values, see `see_with_values` to use others.
+    pub fn see(&self, chess_move: &ChessMove) -> Centipawns {
+        self.see_with_values(chess_move, &PieceValues::default())
+    }
+
+    /// Static exchange evaluation with the given piece values. See `see`.
+    pub fn see_with_values(
+        &self,
+        chess_move: &ChessMove,
+        values: &PieceValues,
+    ) -> Centipawns {
+        let (from, target) = match *chess_move {
+            ChessMove::SimpleMove(from, to)
+            | ChessMove::EnPassant(from, to, _) => (from, to),
+
+            // Castling can't capture anything, and the squares it moves
+            // through have to be safe anyway
+            ChessMove::Castling(_) | ChessMove::NullMove => return 0,
+        };
+
+        let moving_piece = match self.get_piece_at_position(from) {
+            Ok(Some(piece)) => piece,
+            _ => return 0,
+        };
+
+        // We play out the exchange on a scratch board, so that pieces that
+        // have been traded off don't block the pieces behind them
+        let mut board = self.clone();
+        let captured_value = self
+            .get_captured_piece(chess_move)
+            .map_or(0, |p| values.get(p.piece_type));
+
+        if let ChessMove::EnPassant(_, _, capturing) = *chess_move {
+            board.set_piece_at_position(None, capturing).unwrap();
+        }
+        board.set_piece_at_position(None, from).unwrap();
+        board
+            .set_piece_at_position(Some(moving_piece), target)
+            .unwrap();
+
+        // gains[n] is what the side making the nth capture has won, assuming
+        // the exchange stops right after it
+        let mut gains = vec![captured_value];
+        let mut value_on_target = values.get(moving_piece.piece_type);
+        let mut side = self.current_move.flip();
+
+        while let Some((square, piece)) =
+            board.get_least_valuable_attacker(target, side, values)
+        {
+            gains.push(value_on_target - gains[gains.len() - 1]);
+            value_on_target = values.get(piece.piece_type);
+
+            board.set_piece_at_position(None, square).unwrap();
+            board.set_piece_at_position(Some(piece), target).unwrap();
+            side = side.flip();
+        }
+
+        // Either side can choose to stop the exchange instead of recapturing,
+        // so work backwards to find out where it actually stops
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(last);
+        }
+
+        gains[0]
+    }
+
+    // Finds the least valuable piece of `side` that attacks `target`
+    fn get_least_valuable_attacker(
+        &self,
+        target: Square,
+        side: Side,
+        values: &PieceValues,
+    ) -> Option<(Square, Piece)> {
+        // Pawns attack diagonally forwards, so they're found diagonally
+        // backwards from the target
+        let pawn_rank_offset = match side {
+            Side::White => -1,
+            Side::Black => 1,
+        };
+        let pawn_offsets = [
+            Offset {
+                rank: pawn_rank_offset,
+                file: 1,
+            },
+            Offset {
+                rank: pawn_rank_offset,
+                file: -1,
+            },
+        ];
+
+        let attacker_offsets: [(&[Offset], &[PieceType]); 3] = [
+            (&pawn_offsets, &[PieceType::Pawn]),
+            (&KNIGHT_OFFSETS, &[PieceType::Knight]),
+            (&KING_OFFSETS, &[PieceType::King]),
+        ];
+
+        let step_attackers =
+            attacker_offsets.iter().flat_map(|(offsets, piece_types)| {
+                offsets.iter().filter_map(move |offset| {
+                    let square =
+                        self.add_offset_to_position(target, *offset).ok()?;
+                    let piece = self.get_piece_at_position(square).ok()??;
+
+                    (piece.side == side
+                        && piece_types.contains(&piece.piece_type))
+                    .then_some((square, piece))
+                })
+            });
+
+        let ray_offsets: [(&[Offset], &[PieceType]); 2] = [
+            (&ROOK_OFFSETS, &[PieceType::Rook, PieceType::Queen]),
+            (&BISHOP_OFFSETS, &[PieceType::Bishop, PieceType::Queen]),
+        ];
+
+        let ray_attackers =
+            ray_offsets.iter().flat_map(|(offsets, piece_types)| {
+                offsets.iter().filter_map(move |offset| {
+                    let (square, piece) =
+                        self.get_first_piece_on_ray(target, *offset)?;
+
+                    (piece.side == side
+                        && piece_types.contains(&piece.piece_type))
+                    .then_some((square, piece))
+                })
+            });
+
+        step_attackers
+            .chain(ray_attackers)
+            .min_by_key(|(_, piece)| values.get(piece.piece_type))
+    }
+
+    // Walks from `start` in the direction of `offset`, and returns the first
+    // piece found, of either side
+    fn get_first_piece_on_ray(
+        &self,
+        start: Square,
+        offset: Offset,
+    ) -> Option<(Square, Piece)> {
+        let mut square = start;
+        loop {
+            square = self.add_offset_to_position(square, offset).ok()?;
+            if let Some(piece) = self.get_piece_at_position(square).ok()? {
+                return Some((square, piece));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    fn see(art: &str, chess_move: &str) -> Centipawns {
+        let board = Board::from_art(art).unwrap();
+        board.see(&chess_move.try_into().unwrap())
+    }
+
+    #[test]
+    fn wins_undefended_piece() {
+        let gain = see(
+            "....k...\n\
+             ........\n\
+             ........\n\
+             ...n....\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ...RK...\n",
+            "d1d5",
+        );
+
+        assert_eq!(gain, 300);
+    }
+
+    #[test]
+    fn loses_queen_for_defended_pawn() {
+        let gain = see(
+            "....k...\n\
+             ........\n\
+             ....p...\n\
+             ...p....\n\
+             ...Q....\n\
+             ........\n\
+             ........\n\
+             ....K...\n",
+            "d4d5",
+        );
+
+        assert_eq!(gain, -800);
+    }
+
+    #[test]
+    fn trades_evenly() {
+        let gain = see(
+            "....k...\n\
+             ..n.....\n\
+             ........\n\
+             ...n....\n\
+             ........\n\
+             ....N...\n\
+             ........\n\
+             ....K...\n",
+            "e3d5",
+        );
+
+        assert_eq!(gain, 0);
+    }
+
+    #[test]
+    fn counts_attackers_behind_other_attackers() {
+        // The queen on d1 backs up the rook on d2, so white comes out a pawn
+        // ahead even though the pawn is defended by a rook
+        let gain = see(
+            "...rk...\n\
+             ........\n\
+             ........\n\
+             ...p....\n\
+             ........\n\
+             ........\n\
+             ...R....\n\
+             ...QK...\n",
+            "d2d5",
+        );
+
+        assert_eq!(gain, 100);
+    }
+
+    #[test]
+    fn stops_exchange_when_recapturing_loses() {
+        // Black shouldn't take back with the queen, since the pawn would
+        // take the queen after that
+        let gain = see(
+            "...qk...\n\
+             ........\n\
+             ........\n\
+             ...n....\n\
+             ..P.....\n\
+             ........\n\
+             ........\n\
+             ...RK...\n",
+            "d1d5",
+        );
+
+        assert_eq!(gain, 300);
+    }
+
+    #[test]
+    fn quiet_move_to_attacked_square_loses_piece() {
+        let gain = see(
+            "....k...\n\
+             ........\n\
+             ....p...\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ...QK...\n",
+            "d1d5",
+        );
+
+        assert_eq!(gain, -900);
+    }
+
+    #[test]
+    fn works_for_black() {
+        let mut board = Board::from_art(
+            "....k...\n\
+             ...r....\n\
+             ........\n\
+             ...N....\n\
+             ....P...\n\
+             ........\n\
+             ........\n\
+             ....K...\n",
+        )
+        .unwrap();
+        board.flip_current_side();
+
+        assert_eq!(board.see(&"d7d5".try_into().unwrap()), -200);
+    }
+
+    #[test]
+    fn uses_given_values() {
+        let board = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ....p...\n\
+             ...p....\n\
+             ...Q....\n\
+             ........\n\
+             ........\n\
+             ....K...\n",
+        )
+        .unwrap();
+        let values = PieceValues {
+            queen: 1000,
+            ..PieceValues::default()
+        };
+
+        assert_eq!(
+            board.see_with_values(&"d4d5".try_into().unwrap(), &values),
+            -900
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use crate::board::Board;
 
-use super::{chess_move::ChessMove, Offset, Piece, PieceType};
+use super::{
+    chess_move::ChessMove, generation_mode::GenerationMode, Offset, Piece,
+    PieceType,
+};
 
 pub trait StraightMovingPieceMovement {
     fn generate_straight_moves(
@@ -8,6 +11,7 @@ pub trait StraightMovingPieceMovement {
         offsets: &[Offset],
         piece_type: PieceType,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str>;
 }
 
@@ -17,6 +21,7 @@ impl StraightMovingPieceMovement for Board {
         offsets: &[Offset],
         piece_type: PieceType,
         checked: bool,
+        mode: GenerationMode,
     ) -> Result<Vec<ChessMove>, &'static str> {
         let positions = self.get_positions_of_matching_pieces(Piece::new(
             self.current_move,
@@ -37,16 +42,23 @@ impl StraightMovingPieceMovement for Board {
                     .flatten()
                     .map(move |new| (pos, new))
             })
+            // Only the last square of a ray can have a piece on it, in which
+            // case moving there is a capture
+            .filter(|(_, new)| match self.get_piece_at_position(*new) {
+                Ok(None) => mode.includes_quiets(),
+                Ok(Some(_)) => mode.includes_captures(),
+                Err(_) => false,
+            })
             .filter_map(|(old, new)| {
                 // TODO: for now, we keep attempting to create a board
                 // temporarily, to use make_move as validation. This really
                 // should be removed, and we should just have a "validate_move"
                 // method
-                self.new_board_with_moved_piece(old, new, checked).ok()?;
-
-                // If the last statement didn't short circuit and exit with an
-                // error, we know that the move is valid
-                ChessMove::SimpleMove(old, new).into()
+                self.validate_generated_move(
+                    ChessMove::SimpleMove(old, new),
+                    checked,
+                    mode,
+                )
             })
             .collect();
 
@@ -0,0 +1,56 @@
+/// # Zobrist module
+///
+/// Random keys for Zobrist hashing. A position's hash is the xor of the keys
+/// of everything in it, so making a move only has to xor out what changed and
+/// xor in what replaced it.
+///
+/// Boards can be any size, so instead of tables the keys are derived from
+/// what they stand for, by mixing the bits of a unique number for each.
+use super::piece::{Piece, PieceType, Side};
+
+// Tags that keep the numbers of different kinds of keys apart
+const PIECE_TAG: u64 = 1 << 32;
+const SIDE_TAG: u64 = 2 << 32;
+const CASTLING_TAG: u64 = 3 << 32;
+const EN_PASSANT_TAG: u64 = 4 << 32;
+
+/// Key for `piece` standing on the square with the given index
+pub fn piece_key(square_index: usize, piece: Piece) -> u64 {
+    let piece_type = match piece.piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    };
+    let side = match piece.side {
+        Side::White => 0,
+        Side::Black => 1,
+    };
+
+    mix(PIECE_TAG | (square_index as u64) << 4 | side << 3 | piece_type)
+}
+
+/// Key that's in the hash when black is to move
+pub fn side_key() -> u64 {
+    mix(SIDE_TAG)
+}
+
+/// Key for the castling right at the given index of the castling availability
+pub fn castling_key(index: usize) -> u64 {
+    mix(CASTLING_TAG | index as u64)
+}
+
+/// Key for the en passant target being the square with the given index
+pub fn en_passant_key(square_index: usize) -> u64 {
+    mix(EN_PASSANT_TAG | square_index as u64)
+}
+
+// The splitmix64 finalizer, which turns similar numbers into unrelated ones
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
@@ -8,18 +8,22 @@ pub struct CompositeEvaluator {
 }
 
 impl CompositeEvaluator {
-    #[allow(dead_code)]
     pub fn new() -> Self {
         CompositeEvaluator { children: vec![] }
     }
 
-    #[allow(dead_code)]
     pub fn push(&mut self, e: Box<dyn Evaluator>) -> &mut Self {
         self.children.push(e);
         self
     }
 }
 
+impl Default for CompositeEvaluator {
+    fn default() -> Self {
+        CompositeEvaluator::new()
+    }
+}
+
 impl Evaluator for CompositeEvaluator {
     fn evaluate(&self, board: &Board) -> Result<Evaluation, &'static str> {
         self.children.iter().map(|e| e.evaluate(board)).sum()
@@ -1,10 +1,18 @@
-use std::{cmp::Ordering, iter::Sum, ops::Add};
+use std::{
+    cmp::Ordering,
+    iter::Sum,
+    ops::{Add, Neg, Sub},
+};
 
 use crate::board::{game::ChessResult, piece::Side};
 
 pub type Centipawns = i32;
 pub type Depth = u8;
 
+/// How many centipawns ahead a side has to be to have a win probability of
+/// about 91%, see `Evaluation::win_probability`
+pub const WIN_PROBABILITY_SCALE: f64 = 400.0;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Evaluation {
     /// An evaluation where the outcome isn't certain. Centipawns is an estimate
@@ -26,6 +34,53 @@ impl Evaluation {
         }
     }
 
+    /// The opposite of `deepen`, i.e. if it's certain decreases the depth by
+    /// one. Used to pass a search window on to the positions after a move,
+    /// whose results get deepened on the way back.
+    pub fn undeepen(self) -> Self {
+        if let Evaluation::Certain(r, d) = self {
+            Evaluation::Certain(r, d.saturating_sub(1))
+        } else {
+            self
+        }
+    }
+
+    /// This evaluation from `side`'s point of view, see `RelativeEvaluation`
+    pub fn relative_to(self, side: Side) -> RelativeEvaluation {
+        RelativeEvaluation {
+            evaluation: self,
+            side,
+        }
+    }
+
+    /// The chance White wins from here, between 0 and 1. Estimates are put
+    /// on a logistic curve, where being ahead by `WIN_PROBABILITY_SCALE`
+    /// centipawns gives 10 to 1 odds. Checkmates are certain wins or losses,
+    /// and draws count as half a win, like in tournament scoring.
+    pub fn win_probability(self) -> f64 {
+        match self {
+            Evaluation::Estimate(centipawns) => {
+                let centipawns = f64::from(centipawns);
+                1.0 / (1.0 + 10f64.powf(-centipawns / WIN_PROBABILITY_SCALE))
+            }
+            Evaluation::Certain(ChessResult::Draw, _) => 0.5,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), _) => 1.0,
+            Evaluation::Certain(ChessResult::Checkmate(Side::White), _) => 0.0,
+        }
+    }
+
+    /// The estimate that gives White `probability` to win, the opposite of
+    /// `win_probability`. Probabilities of 0 and 1 give the biggest
+    /// estimates that still fit, rather than a checkmate, since they're only
+    /// as certain as the estimates they came from.
+    pub fn from_win_probability(probability: f64) -> Self {
+        let odds = probability / (1.0 - probability);
+        let centipawns = WIN_PROBABILITY_SCALE * odds.log10();
+
+        // Casting saturates, so infinities end up at the ends of the range
+        Evaluation::Estimate(centipawns.round() as Centipawns)
+    }
+
     pub const BEST_FOR_BLACK: Self =
         Evaluation::Certain(ChessResult::Checkmate(Side::White), 0);
     pub const BEST_FOR_WHITE: Self =
@@ -195,3 +250,194 @@ impl Sum for Evaluation {
         iter.fold(Evaluation::Estimate(0), |e1, e2| e1 + e2)
     }
 }
+
+/// An evaluation from the point of view of one side, where greater is better
+/// for that side instead of for White
+///
+/// This lets searchers treat both sides the same way, negamax-style: an
+/// evaluation is negated to get the opponent's point of view, and the best
+/// move is always the one with the greatest evaluation. Results are turned
+/// back into normal evaluations with `absolute` before they're reported.
+///
+/// Only evaluations from the same side's point of view can be compared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelativeEvaluation {
+    evaluation: Evaluation,
+    side: Side,
+}
+
+impl RelativeEvaluation {
+    /// The evaluation as usual, where greater is better for White
+    pub fn absolute(self) -> Evaluation {
+        self.evaluation
+    }
+
+    /// The side whose point of view this is
+    pub fn side(self) -> Side {
+        self.side
+    }
+
+    /// The chance the side whose point of view this is wins, see
+    /// `Evaluation::win_probability`
+    pub fn win_probability(self) -> f64 {
+        match self.side {
+            Side::White => self.evaluation.win_probability(),
+            Side::Black => 1.0 - self.evaluation.win_probability(),
+        }
+    }
+
+    /// See `Evaluation::deepen`
+    pub fn deepen(self) -> Self {
+        self.evaluation.deepen().relative_to(self.side)
+    }
+
+    /// See `Evaluation::undeepen`
+    pub fn undeepen(self) -> Self {
+        self.evaluation.undeepen().relative_to(self.side)
+    }
+}
+
+/// The same evaluation, from the opponent's point of view
+impl Neg for RelativeEvaluation {
+    type Output = RelativeEvaluation;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        self.evaluation.relative_to(self.side.flip())
+    }
+}
+
+/// Makes an estimate better by some centipawns for the side whose point of
+/// view it is. Certain results stay the same.
+impl Add<Centipawns> for RelativeEvaluation {
+    type Output = RelativeEvaluation;
+
+    #[inline]
+    fn add(self, rhs: Centipawns) -> Self::Output {
+        let evaluation = match (self.evaluation, self.side) {
+            (Evaluation::Estimate(c), Side::White) => {
+                Evaluation::Estimate(c + rhs)
+            }
+            (Evaluation::Estimate(c), Side::Black) => {
+                Evaluation::Estimate(c - rhs)
+            }
+            (certain, _) => certain,
+        };
+        evaluation.relative_to(self.side)
+    }
+}
+
+/// Makes an estimate worse by some centipawns for the side whose point of
+/// view it is. Certain results stay the same.
+impl Sub<Centipawns> for RelativeEvaluation {
+    type Output = RelativeEvaluation;
+
+    #[inline]
+    fn sub(self, rhs: Centipawns) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Ord for RelativeEvaluation {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        debug_assert_eq!(self.side, other.side);
+
+        match self.side {
+            Side::White => self.evaluation.cmp(&other.evaluation),
+            Side::Black => other.evaluation.cmp(&self.evaluation),
+        }
+    }
+}
+
+impl PartialOrd for RelativeEvaluation {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_evaluations_are_better_for_their_side() {
+        let winning = Evaluation::Estimate(300);
+        let losing = Evaluation::Estimate(-300);
+
+        assert!(
+            winning.relative_to(Side::White) > losing.relative_to(Side::White)
+        );
+        assert!(
+            winning.relative_to(Side::Black) < losing.relative_to(Side::Black)
+        );
+        assert!(
+            Evaluation::BEST_FOR_BLACK.relative_to(Side::Black)
+                > winning.relative_to(Side::Black)
+        );
+    }
+
+    #[test]
+    fn negating_switches_sides() {
+        let evaluation =
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3);
+        let relative = evaluation.relative_to(Side::White);
+
+        assert_eq!(-relative, evaluation.relative_to(Side::Black));
+        assert_eq!(-(-relative), relative);
+        assert_eq!((-relative).absolute(), evaluation);
+    }
+
+    #[test]
+    fn margins_are_relative_too() {
+        let evaluation = Evaluation::Estimate(100);
+
+        assert_eq!(
+            (evaluation.relative_to(Side::White) + 50).absolute(),
+            Evaluation::Estimate(150)
+        );
+        assert_eq!(
+            (evaluation.relative_to(Side::Black) + 50).absolute(),
+            Evaluation::Estimate(50)
+        );
+        assert_eq!(
+            (Evaluation::BEST_FOR_WHITE.relative_to(Side::Black) - 50)
+                .absolute(),
+            Evaluation::BEST_FOR_WHITE
+        );
+    }
+
+    #[test]
+    fn converts_estimates_to_win_probabilities() {
+        assert_eq!(Evaluation::Estimate(0).win_probability(), 0.5);
+        assert!(
+            (Evaluation::Estimate(400).win_probability() - 10.0 / 11.0).abs()
+                < 1e-9
+        );
+        assert_eq!(Evaluation::BEST_FOR_WHITE.win_probability(), 1.0);
+        assert_eq!(
+            Evaluation::Certain(ChessResult::Draw, 2).win_probability(),
+            0.5
+        );
+        let black_wins = Evaluation::Estimate(-250)
+            .relative_to(Side::Black)
+            .win_probability();
+        assert!(
+            (black_wins - Evaluation::Estimate(250).win_probability()).abs()
+                < 1e-9
+        );
+
+        for centipawns in &[-1000, -37, 0, 150, 2000] {
+            let evaluation = Evaluation::Estimate(*centipawns);
+            assert_eq!(
+                Evaluation::from_win_probability(evaluation.win_probability()),
+                evaluation
+            );
+        }
+        assert_eq!(
+            Evaluation::from_win_probability(1.0),
+            Evaluation::Estimate(Centipawns::MAX)
+        );
+    }
+}
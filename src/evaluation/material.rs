@@ -3,17 +3,80 @@ use crate::board::{
     Board,
 };
 
-use super::{Evaluation, Evaluator};
+use super::{evaluation_result::Centipawns, Evaluation, Evaluator};
+
+/// The value of each type of piece, regardless of side
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PieceValues {
+    pub pawn: Centipawns,
+    pub knight: Centipawns,
+    pub bishop: Centipawns,
+    pub rook: Centipawns,
+    pub queen: Centipawns,
+    pub king: Centipawns,
+}
+
+impl PieceValues {
+    pub fn get(&self, piece_type: PieceType) -> Centipawns {
+        match piece_type {
+            PieceType::Pawn => self.pawn,
+            PieceType::Knight => self.knight,
+            PieceType::Bishop => self.bishop,
+            PieceType::Rook => self.rook,
+            PieceType::Queen => self.queen,
+            PieceType::King => self.king,
+        }
+    }
+}
+
+impl Default for PieceValues {
+    fn default() -> Self {
+        PieceValues {
+            // Conventional wisdom chess values
+            pawn: 100,
+            knight: 300,
+            bishop: 300,
+            rook: 500,
+            queen: 900,
+
+            // The king has an arbitrarily high value, since losing it would
+            // lose the game
+            king: 10000,
+        }
+    }
+}
 
 /// MaterialEvaluator is the simplest evaluator that's still somewhat useful:
 /// it just evaluates chess positions by material. It would make for a very
 /// stereotypically materialistic engine.
-pub struct MaterialEvaluator {}
+#[derive(Clone)]
+pub struct MaterialEvaluator {
+    values: PieceValues,
+}
 
 impl MaterialEvaluator {
-    #[allow(dead_code)]
     pub fn new() -> Self {
-        MaterialEvaluator {}
+        Self::with_values(PieceValues::default())
+    }
+
+    pub fn with_values(values: PieceValues) -> Self {
+        MaterialEvaluator { values }
+    }
+
+    fn get_material_value(&self, piece: Piece) -> i32 {
+        let mut value = self.values.get(piece.piece_type);
+
+        if piece.side == Side::Black {
+            value = -value;
+        }
+
+        value
+    }
+}
+
+impl Default for MaterialEvaluator {
+    fn default() -> Self {
+        MaterialEvaluator::new()
     }
 }
 
@@ -22,38 +85,18 @@ impl Evaluator for MaterialEvaluator {
         let centipawns = board
             .get_squares()
             .iter()
-            .filter_map(|&op| get_material_value(op?).into())
+            .filter_map(|&op| self.get_material_value(op?).into())
             .sum::<i32>();
 
         Ok(Evaluation::Estimate(centipawns))
     }
 }
 
-fn get_material_value(piece: Piece) -> i32 {
-    let mut value = match piece.piece_type {
-        // The king has an arbitrarily high value, since losing it would lose
-        // the game
-        PieceType::King => 10000,
-
-        // Other piece values are conventional wisdom chess values
-        PieceType::Pawn => 100,
-        PieceType::Bishop | PieceType::Knight => 300,
-        PieceType::Rook => 500,
-        PieceType::Queen => 900,
-    };
-
-    if piece.side == Side::Black {
-        value = -value;
-    }
-
-    value
-}
-
 #[cfg(test)]
 mod test {
     use crate::test_board_evaluation;
 
-    use super::MaterialEvaluator;
+    use super::{MaterialEvaluator, PieceValues};
     use crate::board::Board;
 
     test_board_evaluation!(
@@ -77,6 +120,27 @@ mod test {
         -13900
     );
 
+    test_board_evaluation!(
+        uses_given_values,
+        MaterialEvaluator::with_values(PieceValues {
+            pawn: 1,
+            knight: 3,
+            bishop: 3,
+            rook: 5,
+            queen: 9,
+            king: 0,
+        }),
+        "....k...\n\
+         .....q..\n\
+         ........\n\
+         ........\n\
+         ........\n\
+         ........\n\
+         PPPN....\n\
+         ....K...",
+        -3
+    );
+
     test_board_evaluation!(
         one_sided_white,
         MaterialEvaluator::new(),
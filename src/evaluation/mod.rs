@@ -3,6 +3,7 @@ pub mod evaluation_result;
 pub mod material;
 pub mod result;
 
+#[cfg(test)]
 mod test_utils;
 
 use crate::board::Board;
@@ -8,15 +8,21 @@ use super::{
 /// ResultEvaluator is another really simple evaluator. It just figures out if
 /// the game is over, and if it is, it returns that result as a `Certain`
 /// result. Otherwise, it returns a zero `Centipawn` result
+#[derive(Clone)]
 pub struct ResultEvaluator {}
 
 impl ResultEvaluator {
-    #[allow(dead_code)]
     pub fn new() -> Self {
         ResultEvaluator {}
     }
 }
 
+impl Default for ResultEvaluator {
+    fn default() -> Self {
+        ResultEvaluator::new()
+    }
+}
+
 impl Evaluator for ResultEvaluator {
     fn evaluate(&self, board: &Board) -> Result<Evaluation, &'static str> {
         let evaluation = match board.get_game_result()? {
@@ -0,0 +1,3 @@
+pub mod board;
+pub mod evaluation;
+pub mod search;
@@ -1,7 +1,5 @@
-mod board;
-mod evaluation;
-mod search;
+use knight_witch::board::Board;
 
 fn main() {
-    board::Board::default();
+    Board::default();
 }
@@ -1,15 +1,185 @@
+use std::{cmp::Reverse, sync::Arc, time::Instant};
+
 use crate::{
-    board::{chess_move::ChessMove, piece::Side, Board},
+    board::{
+        chess_move::ChessMove,
+        game::ChessResult,
+        generation_mode::GenerationMode,
+        piece::{PieceType, Side},
+        Board,
+    },
     evaluation::{
-        evaluation_result::{Depth, Evaluation},
+        evaluation_result::{
+            Centipawns, Depth, Evaluation, RelativeEvaluation,
+        },
+        material::PieceValues,
         Evaluator,
     },
 };
 
-use super::Searcher;
+use super::{
+    check_deadline, check_node_limit, check_stop,
+    limits::SearchLimits,
+    move_ordering::{MoveOrderer, MoveOrdering},
+    observer::{NoopObserver, SearchObserver, PROGRESS_INTERVAL},
+    stats::SearchStats,
+    transposition_table::{Bound, TranspositionTable},
+    Searcher, SEARCH_ABORTED,
+};
+
+/// Size of the transposition table, unless set otherwise
+const DEFAULT_HASH_SIZE_MB: usize = 16;
+
+/// How many plies past the search depth quiescence search looks at most.
+/// Every check has to be answered, so without a limit a long run of checks
+/// and evasions could keep it going as deep as the game allows.
+const MAX_QUIESCENCE_PLIES: Depth = 8;
+
+/// Width of the aspiration window on either side of the previous evaluation,
+/// unless set otherwise
+const DEFAULT_ASPIRATION_WINDOW: Centipawns = 50;
+
+/// Null moves are only tried this many plies or more from the leaves, since
+/// closer to them the reduced search would go straight to quiescence
+const NULL_MOVE_MIN_DEPTH: Depth = 3;
+
+/// Futility pruning only happens this close to the leaves, where a static
+/// evaluation still says something about what the search would find
+const FUTILITY_MAX_DEPTH: Depth = 2;
+
+/// How far a quiet move is assumed to be able to change the evaluation, for
+/// every ply left to search
+const FUTILITY_MARGIN: Centipawns = 200;
+
+/// Late move reductions only happen this far or further from the leaves
+const LATE_MOVE_REDUCTION_MIN_DEPTH: Depth = 4;
+
+/// How many plies less deep late moves are searched. Reducing by more than
+/// one ply, or closer to the leaves, makes the search miss short mates at the
+/// depth they should be found at.
+const LATE_MOVE_REDUCTION: Depth = 1;
+
+/// How many moves at every position are always searched to full depth
+const FULL_DEPTH_MOVES: usize = 3;
+
+/// Singular extensions are only tried this far or further from the leaves,
+/// since finding out whether a move is singular takes a search of every other
+/// move
+const SINGULAR_EXTENSION_MIN_DEPTH: Depth = 4;
+
+/// How much worse than the transposition table's evaluation every other move
+/// has to be for the move from the table to be singular
+const SINGULAR_MARGIN: Centipawns = 50;
+
+/// How many plies without a capture or pawn move make the game a draw, by the
+/// fifty-move rule
+const FIFTY_MOVE_PLIES: usize = 100;
+
+/// A move, its evaluation, and the line of play expected after it, starting
+/// with the move itself
+pub type SearchedMove = (ChessMove, Evaluation, Vec<ChessMove>);
 
 pub struct AlphabetaSearch<ME, PE> {
     depth: Depth,
+    limits: SearchLimits,
+
+    // The hard deadline of the current search, worked out from `limits` when
+    // it starts
+    deadline: Option<Instant>,
+    observer: Box<dyn SearchObserver>,
+    root_move_order: Vec<ChessMove>,
+    stats: SearchStats,
+
+    // When the current search started, for reporting progress
+    search_start: Instant,
+
+    // The line expected after every root move, as found by the last
+    // completed search
+    principal_variations: Vec<Vec<ChessMove>>,
+
+    // The best root move the current search has found so far, which `search`
+    // falls back on when the search is stopped before it's done
+    best_root_move: Option<(ChessMove, Evaluation)>,
+
+    transposition_table: Arc<TranspositionTable>,
+
+    // Set for searchers made by `helper`, which share the transposition table
+    // with another searcher and leave it to that one to manage
+    is_helper: bool,
+
+    // Hash of the last position searched from, so we can tell when the
+    // transposition table's contents become stale
+    last_root: Option<u64>,
+
+    // Evaluation of the best move the last time `last_root` was searched, to
+    // center the aspiration window on
+    last_root_evaluation: Option<Evaluation>,
+
+    // Whether moves after the first are searched with a null window first
+    principal_variation_search: bool,
+
+    // How far on either side of the last evaluation the root window starts
+    // out, or `None` to always search the root with a full window
+    aspiration_window: Option<Centipawns>,
+
+    // Whether to try passing the turn to prove a position is too good for the
+    // opponent to allow, and whether to check that with a reduced search
+    null_move_pruning: bool,
+    null_move_verification: bool,
+
+    // Set while searching below a null move, where we don't try another one,
+    // so the search can't skip every move with a series of null moves
+    in_null_move_search: bool,
+
+    // Whether to prune near the leaves based on the static evaluation, see
+    // `set_futility_pruning` and `set_reverse_futility_pruning`
+    futility_pruning: bool,
+    reverse_futility_pruning: bool,
+
+    // Whether late quiet moves are searched less deeply at first
+    late_move_reductions: bool,
+
+    // Which kinds of moves are searched a ply deeper, see
+    // `set_check_extensions` and the other setters
+    check_extensions: bool,
+    one_reply_extensions: bool,
+    singular_extensions: bool,
+
+    // How many plies the moves leading to the position being searched were
+    // extended by in total
+    line_extensions: Depth,
+
+    move_ordering: MoveOrdering,
+    move_orderer: MoveOrderer,
+
+    // The moves from the root to the position being searched
+    current_line: Vec<ChessMove>,
+
+    // Whether quiescence search looks at checks as well as captures
+    quiescence_checks: bool,
+
+    // Used to order and prune captures in quiescence search
+    piece_values: PieceValues,
+
+    // How many root moves get an exact evaluation, see `set_multi_pv`
+    multi_pv: usize,
+
+    // Root moves that have already been found by earlier passes of a
+    // MultiPV search, and are skipped by the later ones
+    excluded_root_moves: Vec<ChessMove>,
+
+    // How draws are scored, see `set_contempt`, and the side the engine is
+    // playing, which is the side to move at the root
+    contempt: Option<Centipawns>,
+    engine_side: Side,
+
+    // Hashes of the positions played before the root, see `set_game_history`
+    game_history: Vec<u64>,
+
+    // Hashes of the positions from the root to the position being searched,
+    // along with how many plies each was after the last capture or pawn move
+    line_positions: Vec<(u64, usize)>,
+
     move_sorting_evaluator: ME,
     final_position_evaluator: PE,
 }
@@ -19,34 +189,522 @@ where
     PE: Evaluator,
     ME: Evaluator,
 {
-    #[allow(dead_code)]
     pub fn new(mse: ME, fpe: PE) -> Self {
-        AlphabetaSearch {
-            depth: 4,
+        let mut searcher = AlphabetaSearch {
+            // Both set through `set_limits` below, so they can't disagree
+            depth: 0,
+            limits: SearchLimits::default(),
+            deadline: None,
+            observer: Box::new(NoopObserver),
+            root_move_order: vec![],
+            stats: SearchStats::default(),
+            search_start: Instant::now(),
+            principal_variations: vec![],
+            best_root_move: None,
+            transposition_table: Arc::new(TranspositionTable::new(
+                DEFAULT_HASH_SIZE_MB,
+            )),
+            is_helper: false,
+            last_root: None,
+            last_root_evaluation: None,
+            principal_variation_search: true,
+            aspiration_window: Some(DEFAULT_ASPIRATION_WINDOW),
+            null_move_pruning: true,
+            null_move_verification: false,
+            in_null_move_search: false,
+            futility_pruning: true,
+            reverse_futility_pruning: true,
+            late_move_reductions: true,
+            check_extensions: true,
+            one_reply_extensions: true,
+            singular_extensions: true,
+            line_extensions: 0,
+            move_ordering: MoveOrdering::default(),
+            move_orderer: MoveOrderer::new(),
+            current_line: vec![],
+            quiescence_checks: false,
+            piece_values: PieceValues::default(),
+            multi_pv: 1,
+            excluded_root_moves: vec![],
+            contempt: None,
+            engine_side: Side::White,
+            game_history: vec![],
+            line_positions: vec![],
             move_sorting_evaluator: mse,
             final_position_evaluator: fpe,
+        };
+        searcher.set_limits(&SearchLimits::depth(4));
+
+        searcher
+    }
+
+    /// How many plies ahead searches look, see `Searcher::set_limits`
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+
+    /// Sets whether quiescence search also looks at quiet moves that give
+    /// check. This finds more tactics, at the cost of a bigger search tree.
+    pub fn set_quiescence_checks(&mut self, enabled: bool) -> &mut Self {
+        self.quiescence_checks = enabled;
+        self
+    }
+
+    /// Sets the piece values quiescence search uses to decide which captures
+    /// to look at first, and which ones lose material and aren't worth
+    /// looking at
+    pub fn set_piece_values(&mut self, values: PieceValues) -> &mut Self {
+        self.piece_values = values;
+        self
+    }
+
+    /// Sets how many of the best root moves get an exact evaluation and a
+    /// full principal variation, for showing alternatives to the best move.
+    ///
+    /// Normally only the best move's evaluation is exact, and the others are
+    /// only bounds, because alpha-beta stops looking at a move as soon as it
+    /// knows it's worse than the best one. With `lines` above 1, the root is
+    /// searched once for every line, each time leaving out the moves found
+    /// by the earlier searches, which makes searches that much slower.
+    ///
+    /// `search_order` returns those moves first, followed by the rest.
+    pub fn set_multi_pv(&mut self, lines: usize) -> &mut Self {
+        self.multi_pv = lines.max(1);
+        self
+    }
+
+    /// Sets how the engine scores draws, in centipawns from its own point of
+    /// view, the engine being the side to move when the search starts.
+    /// Positive contempt makes it avoid draws, as if it were that far behind
+    /// in them, which suits playing weaker opponents. Negative contempt makes
+    /// it seek draws out against stronger ones.
+    ///
+    /// This applies to every draw the search comes across: stalemate,
+    /// repetition, the fifty-move rule and insufficient material. `None`, the
+    /// default, scores them as certain draws, which are worse than an even
+    /// estimate for either side, and taken sooner rather than later.
+    pub fn set_contempt(&mut self, contempt: Option<Centipawns>) -> &mut Self {
+        self.contempt = contempt;
+        self
+    }
+
+    /// Sets the hashes of the positions played in the game before the one
+    /// being searched, oldest first, see `Board::get_hash`. A move that
+    /// repeats one of them, or a position earlier in the same line, is
+    /// scored as a draw, since whichever side wanted to could keep repeating
+    /// it.
+    ///
+    /// Only positions after the last capture or pawn move can come up again,
+    /// so the history should start there. How many positions it has is
+    /// then also how far the game is towards the fifty-move rule.
+    pub fn set_game_history(&mut self, hashes: Vec<u64>) -> &mut Self {
+        self.game_history = hashes;
+        self
+    }
+
+    /// Sets whether to use principal variation search, which is on by default
+    ///
+    /// With good move ordering the first move at a node is usually the best
+    /// one, so every move after it is first searched with a null window, which
+    /// only tells whether the move is better. Only moves that are get searched
+    /// again to find out by how much.
+    pub fn set_principal_variation_search(
+        &mut self,
+        enabled: bool,
+    ) -> &mut Self {
+        self.principal_variation_search = enabled;
+        self
+    }
+
+    /// Sets how far on either side of the last evaluation of the same position
+    /// the window at the root starts out. Narrow windows cut off more, but
+    /// have to be searched again when the evaluation falls outside them.
+    /// `None` always searches the root with a full window.
+    pub fn set_aspiration_window(
+        &mut self,
+        width: Option<Centipawns>,
+    ) -> &mut Self {
+        self.aspiration_window = width;
+        self
+    }
+
+    /// Sets whether to use null move pruning, which is on by default
+    ///
+    /// If the side to move could pass and still be doing too well for the
+    /// opponent to allow the position, a reduced search of the position after
+    /// passing is usually enough to skip searching it properly. This goes
+    /// wrong in zugzwang, where every move makes things worse, so null moves
+    /// aren't tried in check or when the side to move has little more than
+    /// pawns left.
+    pub fn set_null_move_pruning(&mut self, enabled: bool) -> &mut Self {
+        self.null_move_pruning = enabled;
+        self
+    }
+
+    /// Sets whether a null move cutoff has to be confirmed by a reduced search
+    /// of the position without passing, which catches zugzwang positions the
+    /// other checks miss. Off by default.
+    pub fn set_null_move_verification(&mut self, enabled: bool) -> &mut Self {
+        self.null_move_verification = enabled;
+        self
+    }
+
+    /// Sets whether to use futility pruning, which is on by default
+    ///
+    /// Close to the leaves, quiet moves are skipped when the static
+    /// evaluation is so bad that even a generous margin wouldn't bring it
+    /// back into the window.
+    pub fn set_futility_pruning(&mut self, enabled: bool) -> &mut Self {
+        self.futility_pruning = enabled;
+        self
+    }
+
+    /// Sets whether to use reverse futility pruning, which is on by default
+    ///
+    /// Close to the leaves, positions aren't searched at all when the static
+    /// evaluation is so good that the opponent won't allow them, even after
+    /// taking off a generous margin.
+    pub fn set_reverse_futility_pruning(&mut self, enabled: bool) -> &mut Self {
+        self.reverse_futility_pruning = enabled;
+        self
+    }
+
+    /// Sets whether to use late move reductions, which is on by default
+    ///
+    /// Quiet moves that are sorted late are unlikely to be best, so they're
+    /// searched a ply less deep, and only searched to full depth if that
+    /// finds them to be better than the best move so far.
+    pub fn set_late_move_reductions(&mut self, enabled: bool) -> &mut Self {
+        self.late_move_reductions = enabled;
+        self
+    }
+
+    /// Sets whether moves that give check are searched a ply deeper, which is
+    /// on by default
+    ///
+    /// Like the other extensions, this lets the search follow forcing lines
+    /// past its depth, for example to find mating attacks. Extensions along
+    /// a single line never add up to more than the search depth, so they
+    /// can't make the search tree explode.
+    pub fn set_check_extensions(&mut self, enabled: bool) -> &mut Self {
+        self.check_extensions = enabled;
+        self
+    }
+
+    /// Sets whether the only legal move in a position is searched a ply
+    /// deeper, which is on by default
+    pub fn set_one_reply_extensions(&mut self, enabled: bool) -> &mut Self {
+        self.one_reply_extensions = enabled;
+        self
+    }
+
+    /// Sets whether to use singular extensions, which is on by default
+    ///
+    /// When the transposition table has a move that was much better than
+    /// every other move the last time a position was searched, the position
+    /// probably hinges on that move, so it's searched a ply deeper. Finding
+    /// out whether the other moves are much worse takes a shallow search of
+    /// each of them.
+    pub fn set_singular_extensions(&mut self, enabled: bool) -> &mut Self {
+        self.singular_extensions = enabled;
+        self
+    }
+
+    /// Sets how moves are ordered, see `MoveOrdering`. Whatever the ordering,
+    /// the best move from the transposition table is always tried first.
+    pub fn set_move_ordering(&mut self, ordering: MoveOrdering) -> &mut Self {
+        self.move_ordering = ordering;
+        self
+    }
+
+    /// Resizes the transposition table to take up roughly `megabytes` of
+    /// memory. This throws away anything that was in the table.
+    pub fn set_hash_size(&mut self, megabytes: usize) -> &mut Self {
+        self.transposition_table = Arc::new(TranspositionTable::new(megabytes));
+        self
+    }
+
+    /// A searcher with the same settings and evaluators that shares this
+    /// one's transposition table, to help search the same position from
+    /// another thread. See `LazySmpSearch` and `RootSplitSearch`.
+    ///
+    /// Helpers only look for the best move, whatever `set_multi_pv` was set
+    /// to, and don't have an observer.
+    pub fn helper(&self) -> Self
+    where
+        ME: Clone,
+        PE: Clone,
+    {
+        AlphabetaSearch {
+            depth: self.depth,
+            limits: self.limits.clone(),
+            deadline: self.deadline,
+            observer: Box::new(NoopObserver),
+            root_move_order: vec![],
+            stats: SearchStats::default(),
+            search_start: Instant::now(),
+            principal_variations: vec![],
+            best_root_move: None,
+            transposition_table: Arc::clone(&self.transposition_table),
+            is_helper: true,
+            last_root: None,
+            last_root_evaluation: None,
+            principal_variation_search: self.principal_variation_search,
+            aspiration_window: self.aspiration_window,
+            null_move_pruning: self.null_move_pruning,
+            null_move_verification: self.null_move_verification,
+            in_null_move_search: false,
+            futility_pruning: self.futility_pruning,
+            reverse_futility_pruning: self.reverse_futility_pruning,
+            late_move_reductions: self.late_move_reductions,
+            check_extensions: self.check_extensions,
+            one_reply_extensions: self.one_reply_extensions,
+            singular_extensions: self.singular_extensions,
+            line_extensions: 0,
+            move_ordering: self.move_ordering,
+            move_orderer: MoveOrderer::new(),
+            current_line: vec![],
+            quiescence_checks: self.quiescence_checks,
+            piece_values: self.piece_values,
+            multi_pv: 1,
+            excluded_root_moves: vec![],
+            contempt: self.contempt,
+            engine_side: self.engine_side,
+            game_history: self.game_history.clone(),
+            line_positions: vec![],
+            move_sorting_evaluator: self.move_sorting_evaluator.clone(),
+            final_position_evaluator: self.final_position_evaluator.clone(),
+        }
+    }
+
+    /// Gets ready to search from `board`, resetting the statistics and
+    /// everything else that's kept per search. `search_order` does this
+    /// itself, this is for searching root moves with `search_root_move`.
+    pub fn start_search(&mut self, board: &Board) {
+        self.stats = SearchStats::default();
+        self.search_start = Instant::now();
+        self.best_root_move = None;
+        self.deadline =
+            self.limits.get_deadlines(self.search_start).map(|d| d.hard);
+
+        let root = board.get_hash();
+        if self.last_root != Some(root) {
+            if !self.is_helper {
+                self.transposition_table.new_search();
+            }
+            self.move_orderer.new_search();
+            self.last_root = Some(root);
+            self.last_root_evaluation = None;
+        }
+
+        self.current_line.clear();
+        self.line_extensions = 0;
+
+        self.engine_side = board.get_current_side();
+        self.line_positions.clear();
+        self.line_positions.push((root, self.game_history.len()));
+    }
+
+    /// Searches a single root move of `board`, only looking for whether it's
+    /// better than `alpha`, the best evaluation another root move got. Like
+    /// the moves `search_order` doesn't find to be best, moves that aren't
+    /// better get a bound of at most `alpha` rather than an exact evaluation.
+    ///
+    /// This lets several searchers split up the root moves between them, see
+    /// `RootSplitSearch`. Call `start_search` before the first move.
+    pub fn search_root_move(
+        &mut self,
+        board: &Board,
+        chess_move: &ChessMove,
+        alpha: Evaluation,
+    ) -> Result<SearchedMove, &'static str> {
+        let side = board.get_current_side();
+        let (_, beta) = relative_window(
+            side,
+            Evaluation::BEST_FOR_BLACK,
+            Evaluation::BEST_FOR_WHITE,
+        );
+        let alpha = alpha.relative_to(side);
+
+        let mut moved_board = board.clone();
+        moved_board.make_move(chess_move.clone(), false)?;
+
+        // The same extensions as for the root moves `search_order` searches
+        let only_move = board.generate_moves(true)?.len() == 1;
+        let extension = if (self.check_extensions
+            && moved_board.check_king_threat()?)
+            || (self.one_reply_extensions && only_move)
+        {
+            1
+        } else {
+            0
+        };
+        if extension > 0 {
+            self.stats.extensions += 1;
+        }
+        let depth = self.depth.saturating_add(extension);
+        self.line_extensions += extension;
+
+        // With principal variation search, a null window is enough to find
+        // out the move is no better, which is what most moves turn out to be
+        let result = if self.principal_variation_search {
+            self.search_child(
+                board,
+                &moved_board,
+                chess_move,
+                depth,
+                alpha,
+                alpha,
+            )
+            .and_then(|(evaluation, continuation)| {
+                if evaluation > alpha {
+                    self.search_child(
+                        board,
+                        &moved_board,
+                        chess_move,
+                        depth,
+                        alpha,
+                        beta,
+                    )
+                } else {
+                    Ok((evaluation, continuation))
+                }
+            })
+        } else {
+            self.search_child(
+                board,
+                &moved_board,
+                chess_move,
+                depth,
+                alpha,
+                beta,
+            )
+        };
+        self.line_extensions -= extension;
+        self.stats.elapsed = self.search_start.elapsed();
+        let (evaluation, continuation) = result?;
+
+        let mut line = vec![chess_move.clone()];
+        line.extend(continuation);
+        Ok((chess_move.clone(), evaluation.absolute(), line))
+    }
+
+    // Returns `SEARCH_ABORTED` once the search is stopped, or the deadline or
+    // the node limit is reached
+    fn check_limits(&self) -> Result<(), &'static str> {
+        check_stop(self.limits.stop.as_ref())?;
+        check_deadline(self.deadline)?;
+        check_node_limit(self.stats.nodes, self.limits.nodes)
+    }
+
+    // Tells the observer how the search is going, every `PROGRESS_INTERVAL`
+    // nodes
+    fn report_progress(&mut self) {
+        if self.stats.nodes.is_multiple_of(PROGRESS_INTERVAL) {
+            self.stats.elapsed = self.search_start.elapsed();
+            self.observer.on_progress(&self.stats);
         }
     }
 
+    // Searches every move in `board`, negamax-style. The window is from the
+    // point of view of the side to move: `alpha` is what they can already
+    // get elsewhere, and `beta` is the most the opponent lets them get.
+    // Evaluations in the returned moves are absolute, as usual, and sorted
+    // best first for the side to move.
     fn search_order_impl(
-        &self,
+        &mut self,
         board: &Board,
         depth: Depth,
-        mut alpha: Evaluation,
-        mut beta: Evaluation,
-    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
-        let moves = board.generate_moves(true)?;
+        mut alpha: RelativeEvaluation,
+        beta: RelativeEvaluation,
+    ) -> Result<Vec<SearchedMove>, &'static str> {
+        self.check_limits()?;
+        self.stats.count_node(self.current_line.len());
+        self.report_progress();
+
+        let hash = board.get_hash();
+        let side = board.get_current_side();
+        let is_root = self.current_line.is_empty();
+        let tt_entry = self.transposition_table.get(hash);
+        if tt_entry.is_some() {
+            self.stats.tt_hits += 1;
+        }
+
+        // If we've already searched this position at least as deeply, we may
+        // be able to reuse the result. At the root we always need every move
+        // evaluated, so we can't stop early there.
+        if let Some(entry) = tt_entry.as_ref().filter(|e| e.depth >= depth) {
+            let evaluation = entry.evaluation.relative_to(side);
+            let usable = match entry.bound.relative_to(side) {
+                Bound::Exact => true,
+                Bound::Lower => evaluation > beta,
+                Bound::Upper => evaluation < alpha,
+            };
+
+            if usable && !is_root {
+                // We don't know how the line continues after the stored move,
+                // so the principal variation stops there. A move that isn't
+                // legal here means the entry is for a different position with
+                // the same hash, so we search this one after all.
+                let cutoff = match entry.best_move.clone() {
+                    Some(m) if board.generate_moves(true)?.contains(&m) => {
+                        Some((m.clone(), vec![m]))
+                    }
+                    Some(_) => None,
+                    None => Some((ChessMove::NullMove, vec![])),
+                };
+                if let Some((best_move, line)) = cutoff {
+                    self.stats.tt_cutoffs += 1;
+                    return Ok(vec![(best_move, entry.evaluation, line)]);
+                }
+            }
+        }
+
+        // Every move is forced when in check, so none of the pruning below
+        // happens then, or at the root, where we need every move evaluated
+        let in_check = board.check_king_threat()?;
+        let static_evaluation = if is_root || in_check {
+            None
+        } else {
+            Some(self.evaluate(board)?)
+        };
+
+        // We only know the real evaluation is past the window, so we return
+        // a bound strictly outside it, without a move
+        let static_evaluation = static_evaluation.map(|e| e.relative_to(side));
+        if let Some(evaluation) =
+            self.reverse_futility_evaluation(static_evaluation, depth, beta)
+        {
+            self.stats.reverse_futility_cutoffs += 1;
+            return Ok(vec![(ChessMove::NullMove, evaluation, vec![])]);
+        }
+        if let Some(evaluation) = self.null_move_evaluation(
+            board,
+            static_evaluation,
+            depth,
+            alpha,
+            beta,
+        )? {
+            self.stats.null_move_cutoffs += 1;
+            return Ok(vec![(ChessMove::NullMove, evaluation, vec![])]);
+        }
+
+        let mut moves = board.generate_moves(true)?;
 
         if moves.is_empty() {
             return Ok(vec![(
                 ChessMove::NullMove,
-                self.final_position_evaluator.evaluate(board)?,
+                self.evaluate(board)?,
+                vec![],
             )]);
         }
 
-        // Sort the moves by preference order of the current player
-        // Because we have to make the moves on a board to figure out this
-        // order, we also cache the boards here
+        if is_root {
+            moves.retain(|m| !self.excluded_root_moves.contains(m));
+        }
+
+        // We need to make every move on a board to search it anyway, so we
+        // cache the boards here
         let moved_boards_iter = moves.into_iter().map(|m| {
             let mut moved_board = board.clone();
 
@@ -56,168 +714,1845 @@ where
             (m, moved_board)
         });
 
-        if depth <= 1 {
-            let mut moves_in_order: Vec<_> = moved_boards_iter
-                .map(|(m, b)| {
-                    (m, self.final_position_evaluator.evaluate(&b).unwrap())
-                })
-                .collect();
-            moves_in_order.sort_unstable_by_key(|(_, e)| *e);
-            if board.get_current_side() == Side::Black {
-                moves_in_order.reverse();
+        // Give every move a score, higher for moves that should be searched
+        // earlier, and sort by it
+        let mut moved_boards = match self.move_ordering {
+            MoveOrdering::Heuristics => {
+                let ply = self.current_line.len();
+                let previous_move = self.current_line.last();
+                moved_boards_iter
+                    .map(|(m, b)| {
+                        let score = self.move_orderer.score(
+                            board,
+                            &m,
+                            ply,
+                            previous_move,
+                            &self.piece_values,
+                        );
+                        (m, b, score)
+                    })
+                    .collect()
             }
+            MoveOrdering::Evaluator => {
+                self.score_by_evaluation(side, moved_boards_iter)
+            }
+        };
+        moved_boards.sort_by_key(|(_, _, score)| Reverse(*score));
+        let best_score = moved_boards[0].2;
 
-            return Ok(moves_in_order);
+        // The move that was best the last time we saw this position is likely
+        // to be good again, so try it first
+        if let Some(tt_move) =
+            tt_entry.as_ref().and_then(|e| e.best_move.clone())
+        {
+            if let Some(index) =
+                moved_boards.iter().position(|(m, _, _)| *m == tt_move)
+            {
+                let tt_moved_board = moved_boards.remove(index);
+                moved_boards.insert(0, tt_moved_board);
+            }
         }
 
-        let mut moved_boards = moved_boards_iter.collect::<Vec<_>>();
-
-        // Note: this sorts in ascending order, meaning the moves best for
-        // black are at the start.
-        moved_boards.sort_unstable_by_key(|(_, b)| {
-            self.move_sorting_evaluator.evaluate(b)
-        });
-        if board.get_current_side() == Side::White {
-            moved_boards.reverse();
+        // At the root, prefer the order we were given, if any. The sort is
+        // stable, so moves we weren't told about keep their relative order.
+        if is_root && !self.root_move_order.is_empty() {
+            let root_move_order = &self.root_move_order;
+            moved_boards.sort_by_key(|(m, _, _)| {
+                root_move_order
+                    .iter()
+                    .position(|r| r == m)
+                    .unwrap_or(usize::MAX)
+            });
         }
 
+        // If the last search of this position found a move that was good for
+        // us, it may be the only good one, see `set_singular_extensions`
+        let singular_candidate = tt_entry
+            .filter(|e| {
+                self.singular_extensions
+                    && !is_root
+                    && depth >= SINGULAR_EXTENSION_MIN_DEPTH
+                    && e.depth.saturating_add(3) >= depth
+                    && matches!(
+                        e.bound.relative_to(side),
+                        Bound::Exact | Bound::Lower
+                    )
+            })
+            .and_then(|e| match e.evaluation {
+                Evaluation::Estimate(_) => {
+                    Some((e.best_move?, e.evaluation.relative_to(side)))
+                }
+                Evaluation::Certain(..) => None,
+            });
+        let only_move = moved_boards.len() == 1;
+
+        let original_alpha = alpha;
+        let mut cut_off = false;
+
         let mut moves_seen_so_far = vec![];
-        for (m, b) in moved_boards {
-            let search_result =
-                self.search_order_impl(&b, depth - 1, alpha, beta)?;
+        for (move_number, (m, b, score)) in (1..).zip(moved_boards) {
+            if is_root {
+                self.observer.on_current_move(&m, move_number, depth);
+            }
 
-            let current_evaluation =
-                if let Some((_, mut opponents_best_evaluation)) =
-                    search_result.into_iter().next()
-                {
-                    opponents_best_evaluation =
-                        opponents_best_evaluation.deepen();
-                    opponents_best_evaluation
-                } else {
-                    self.final_position_evaluator.evaluate(&b)?
-                };
+            // Since cutoffs only happen on evaluations strictly outside the
+            // window, a window that's just the best evaluation so far is
+            // enough to tell whether a move is any better
+            let best_so_far = alpha;
+
+            // Captures and checks change the position too much to be judged
+            // by the static evaluation or a shallower search
+            let gives_check = b.check_king_threat()?;
+            let is_quiet = static_evaluation.is_some()
+                && board.get_captured_piece(&m).is_none()
+                && !gives_check;
 
-            moves_seen_so_far.push((m, current_evaluation));
-
-            match board.get_current_side() {
-                // If the opponent has a better move elsewhere in
-                // the search tree, then they would never chose to go into
-                // this variation, so we can just stop searching through
-                // this branch for now
-                //
-                // If not, then we just update the cutoffs, since we've
-                // maybe improved our bounds on the best possible moves
-                Side::White => {
-                    if current_evaluation > beta {
-                        break;
-                    } else if current_evaluation > alpha {
-                        alpha = current_evaluation
+            // Moves that can't get back to the best so far even with a
+            // generous margin are skipped. They keep that margin as their
+            // evaluation, so if nothing better is found, the result is still
+            // a bound on what they could have been worth.
+            let futility_evaluation = self
+                .futility_evaluation(static_evaluation, depth)
+                .filter(|_| is_quiet && !moves_seen_so_far.is_empty())
+                .filter(|e| *e < best_so_far);
+            if let Some(evaluation) = futility_evaluation {
+                self.stats.futility_pruned_moves += 1;
+                moves_seen_so_far.push((
+                    m.clone(),
+                    evaluation.absolute(),
+                    vec![m],
+                ));
+                continue;
+            }
+
+            // Moves the move ordering can't tell apart from the best one
+            // could just as well have been sorted first, so only the ones
+            // it actually thinks are worse get reduced
+            let reduction = if is_quiet
+                && self.late_move_reductions
+                && depth >= LATE_MOVE_REDUCTION_MIN_DEPTH
+                && move_number > FULL_DEPTH_MOVES
+                && score < best_score
+            {
+                LATE_MOVE_REDUCTION
+            } else {
+                0
+            };
+
+            // Forcing moves are searched a ply deeper, so that lines of them
+            // don't get cut off at the horizon
+            let extension = if self.line_extensions >= self.depth {
+                0
+            } else if (self.check_extensions && gives_check)
+                || (self.one_reply_extensions && only_move)
+            {
+                1
+            } else {
+                match &singular_candidate {
+                    Some((tt_move, e)) if *tt_move == m => {
+                        Depth::from(self.is_singular(board, &m, depth, *e)?)
                     }
+                    _ => 0,
                 }
-                Side::Black => {
-                    if current_evaluation < alpha {
-                        break;
-                    } else if current_evaluation < beta {
-                        beta = current_evaluation
-                    }
+            };
+            if extension > 0 {
+                self.stats.extensions += 1;
+            }
+            let child_depth = depth.saturating_add(extension);
+            self.line_extensions += extension;
+
+            let reduced_result = if reduction > 0 {
+                self.stats.reduced_moves += 1;
+                let result = self.search_child(
+                    board,
+                    &b,
+                    &m,
+                    child_depth - reduction,
+                    best_so_far,
+                    best_so_far,
+                )?;
+
+                if result.0 > best_so_far {
+                    self.stats.reduction_re_searches += 1;
+                    None
+                } else {
+                    Some(result)
+                }
+            } else {
+                None
+            };
+
+            let (current_evaluation, continuation) = if let Some(result) =
+                reduced_result
+            {
+                result
+            } else if !self.principal_variation_search
+                || moves_seen_so_far.is_empty()
+            {
+                self.search_child(board, &b, &m, child_depth, alpha, beta)?
+            } else {
+                let (evaluation, continuation) = self.search_child(
+                    board,
+                    &b,
+                    &m,
+                    child_depth,
+                    best_so_far,
+                    best_so_far,
+                )?;
+
+                // If it is better, but not good enough to cut off, we
+                // need to know exactly how good it is
+                if evaluation > alpha && evaluation < beta {
+                    self.search_child(board, &b, &m, child_depth, alpha, beta)?
+                } else {
+                    (evaluation, continuation)
                 }
+            };
+            self.line_extensions -= extension;
+
+            // Later passes of a MultiPV search only find alternatives to the
+            // best move, so they don't report anything as the best move
+            if is_root && self.excluded_root_moves.is_empty() {
+                let is_new_best = moves_seen_so_far
+                    .iter()
+                    .all(|(_, e, _)| current_evaluation > e.relative_to(side));
+                if is_new_best {
+                    let evaluation = current_evaluation.absolute();
+                    self.best_root_move = Some((m.clone(), evaluation));
+                    self.observer.on_new_best_move(&m, evaluation, depth);
+                }
+            }
+
+            let mut line = vec![m.clone()];
+            line.extend(continuation);
+            moves_seen_so_far.push((m, current_evaluation.absolute(), line));
+
+            // If the opponent has a better move elsewhere in the search tree,
+            // then they would never chose to go into this variation, so we
+            // can just stop searching through this branch for now
+            //
+            // If not, then we just update the cutoffs, since we've maybe
+            // improved our bounds on the best possible moves
+            if current_evaluation > beta {
+                cut_off = true;
+                break;
+            } else if current_evaluation > alpha {
+                alpha = current_evaluation;
+            }
+        }
+
+        // The move that caused the cutoff is the last one searched, and is
+        // likely to cause cutoffs in similar positions too
+        if cut_off {
+            if let Some((m, _, _)) = moves_seen_so_far.last() {
+                self.move_orderer.record_cutoff(
+                    board,
+                    m,
+                    self.current_line.len(),
+                    depth,
+                    self.current_line.last(),
+                );
             }
         }
 
-        // Note: this sorts in ascending order, meaning the moves best for
-        // black are at the start.
-        moves_seen_so_far.sort_unstable_by_key(|(_, eval)| *eval);
-        if board.get_current_side() == Side::White {
-            moves_seen_so_far.reverse()
+        // The sort is stable, so out of moves that are equally good, the one
+        // searched first stays first
+        moves_seen_so_far
+            .sort_by_key(|(_, eval, _)| Reverse(eval.relative_to(side)));
+
+        if cut_off {
+            self.stats.beta_cutoffs += 1;
+            if moves_seen_so_far.len() == 1 {
+                self.stats.first_move_beta_cutoffs += 1;
+            }
+        }
+
+        // If we stopped early, or none of the moves were good enough to fit
+        // in our bounds, we only know a bound on the real evaluation
+        let (best_move, best_evaluation, _) = moves_seen_so_far[0].clone();
+        let bound = if cut_off {
+            Bound::Lower
+        } else if best_evaluation.relative_to(side) <= original_alpha {
+            Bound::Upper
+        } else {
+            Bound::Exact
+        };
+        // The result at the root of a MultiPV pass leaves out the moves
+        // that are actually best, so it doesn't describe the position
+        if !is_root || self.excluded_root_moves.is_empty() {
+            self.transposition_table.store(
+                hash,
+                depth,
+                bound.relative_to(side),
+                best_evaluation,
+                Some(best_move),
+            );
         }
 
         Ok(moves_seen_so_far)
     }
-}
 
-impl<ME, PE> Searcher for AlphabetaSearch<ME, PE>
-where
-    PE: Evaluator,
-    ME: Evaluator,
-{
-    fn search_order(
-        &self,
+    // If passing the turn in `board` still leaves the side to move doing too
+    // well for the opponent, returns how well, so the search of `board` can
+    // be skipped
+    fn null_move_evaluation(
+        &mut self,
         board: &Board,
-    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
-        self.search_order_impl(
-            board,
-            self.depth,
-            Evaluation::BEST_FOR_BLACK, // worst result for white
-            Evaluation::BEST_FOR_WHITE, // worst result for black
-        )
-    }
-}
+        static_evaluation: Option<RelativeEvaluation>,
+        depth: Depth,
+        alpha: RelativeEvaluation,
+        beta: RelativeEvaluation,
+    ) -> Result<Option<Evaluation>, &'static str> {
+        let static_evaluation = match static_evaluation {
+            Some(evaluation) => evaluation,
+            None => return Ok(None),
+        };
 
-#[cfg(test)]
-mod tests {
-    use crate::board::game::ChessResult;
-    use crate::evaluation::composite::CompositeEvaluator;
-    use crate::evaluation::material::MaterialEvaluator;
-    use crate::evaluation::result::ResultEvaluator;
+        // With only pawns or a single minor piece left, zugzwang is common
+        // enough that passing doesn't tell us much
+        let side = board.get_current_side();
+        let enough_material =
+            get_non_pawn_material(board, side, &self.piece_values)
+                >= self.piece_values.rook;
 
-    use super::*;
+        if !self.null_move_pruning
+            || self.in_null_move_search
+            || depth < NULL_MOVE_MIN_DEPTH
+            || !enough_material
+        {
+            return Ok(None);
+        }
 
-    #[test]
-    fn finds_mate_in_two() {
-        let board = Board::from_art(
-            ".k......\n\
-             ........\n\
-             ..K.....\n\
-             ..R.....\n\
-             ........\n\
-             ........\n\
-             ........\n\
-             ........\n",
-        )
-        .unwrap();
+        // Passing is only going to be good enough if the position already is
+        if static_evaluation < beta {
+            return Ok(None);
+        }
 
-        let mse = MaterialEvaluator::new();
+        // Deeper searches can afford to be reduced more
+        let reduction = 2 + depth / 6;
 
-        let fpe = ResultEvaluator::new();
+        let mut null_board = board.clone();
+        null_board.make_move(ChessMove::NullMove, false)?;
 
-        let mut searcher = AlphabetaSearch::new(mse, fpe);
-        searcher.depth = 4;
+        self.in_null_move_search = true;
+        let result = self
+            .search_child(
+                board,
+                &null_board,
+                &ChessMove::NullMove,
+                depth - reduction,
+                beta,
+                beta,
+            )
+            .and_then(|(evaluation, _)| {
+                // A mate found after passing isn't one the opponent has to
+                // allow, so only estimates can be trusted
+                if !matches!(evaluation.absolute(), Evaluation::Estimate(_))
+                    || evaluation <= beta
+                {
+                    return Ok(None);
+                }
+                if !self.null_move_verification {
+                    return Ok(Some(evaluation.absolute()));
+                }
 
-        let (_, evaluation) = searcher.search(&board).unwrap();
-        assert_eq!(
-            evaluation,
-            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
-        );
+                // Make sure we'd actually find a move that's good enough
+                let verification = self.search_order_impl(
+                    board,
+                    depth - reduction,
+                    alpha,
+                    beta,
+                )?;
+                Ok((verification[0].1.relative_to(side) > beta)
+                    .then_some(evaluation.absolute()))
+            });
+        self.in_null_move_search = false;
+
+        result
     }
 
-    #[test]
-    fn finds_mate_in_four() {
-        let board = Board::from_art(
-            "..N.....\n\
-             ........\n\
-             k..K....\n\
-             ........\n\
-             p.PB....\n\
-             P.......\n\
-             ........\n\
-             ........\n",
-        )
-        .unwrap();
+    // If a position `depth` plies from the leaves is so much better for the
+    // side to move than the window that it's not worth searching, returns
+    // how much better it's assumed to be at least
+    fn reverse_futility_evaluation(
+        &self,
+        static_evaluation: Option<RelativeEvaluation>,
+        depth: Depth,
+        beta: RelativeEvaluation,
+    ) -> Option<Evaluation> {
+        if !self.reverse_futility_pruning || depth > FUTILITY_MAX_DEPTH {
+            return None;
+        }
 
-        let mse = MaterialEvaluator::new();
+        let margin = FUTILITY_MARGIN * Centipawns::from(depth);
+        static_evaluation
+            .filter(|e| matches!(e.absolute(), Evaluation::Estimate(_)))
+            .map(|e| e - margin)
+            .filter(|e| *e > beta)
+            .map(RelativeEvaluation::absolute)
+    }
 
-        let re2 = ResultEvaluator::new();
-        let me2 = MaterialEvaluator::new();
-        let mut fpe = CompositeEvaluator::new();
-        fpe.push(Box::new(re2)).push(Box::new(me2));
+    // The best a quiet move for the side to move in a position `depth` plies
+    // from the leaves is assumed to be able to do, or `None` if we don't
+    // prune there
+    fn futility_evaluation(
+        &self,
+        static_evaluation: Option<RelativeEvaluation>,
+        depth: Depth,
+    ) -> Option<RelativeEvaluation> {
+        if !self.futility_pruning || depth > FUTILITY_MAX_DEPTH {
+            return None;
+        }
 
-        let mut searcher = AlphabetaSearch::new(mse, fpe);
-        searcher.depth = 8;
+        let margin = FUTILITY_MARGIN * Centipawns::from(depth);
+        static_evaluation
+            .filter(|e| matches!(e.absolute(), Evaluation::Estimate(_)))
+            .map(|e| e + margin)
+    }
 
-        let (_, evaluation) = searcher.search(&board).unwrap();
-        assert_eq!(
-            evaluation,
-            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 7)
-        );
+    // Searches `board`, the position after a move in `parent`, returning its
+    // evaluation and the line expected after it. Like the window, the
+    // evaluation is from the point of view of the side that made the move.
+    fn search_child(
+        &mut self,
+        parent: &Board,
+        board: &Board,
+        chess_move: &ChessMove,
+        depth: Depth,
+        alpha: RelativeEvaluation,
+        beta: RelativeEvaluation,
+    ) -> Result<(RelativeEvaluation, Vec<ChessMove>), &'static str> {
+        // Whether a position is drawn by repetition or the fifty-move rule
+        // depends on how it was reached, so the evaluators can't tell
+        let hash = board.get_hash();
+        let plies_since_reset = self.plies_since_reset(parent, chess_move);
+        if self.is_draw(board, hash, plies_since_reset) {
+            let evaluation = self.draw_evaluation().deepen();
+            return Ok((
+                evaluation.relative_to(parent.get_current_side()),
+                vec![],
+            ));
+        }
+
+        // The window is the other way around for the opponent, and mates are
+        // a ply closer for them
+        let (alpha, beta) = (-beta.undeepen(), -alpha.undeepen());
+
+        // Quiescence search doesn't keep track of the moves it looks at,
+        // so principal variations end where the main search does
+        if depth <= 1 {
+            let evaluation = self
+                .quiescence(board, alpha, beta, self.quiescence_checks, 0)?
+                .deepen();
+            return Ok((-evaluation, vec![]));
+        }
+
+        self.current_line.push(chess_move.clone());
+        self.line_positions.push((hash, plies_since_reset));
+        let search_result =
+            self.search_order_impl(board, depth - 1, alpha, beta);
+        self.line_positions.pop();
+        self.current_line.pop();
+
+        let side = board.get_current_side();
+        if let Some((_, opponents_best_evaluation, line)) =
+            search_result?.into_iter().next()
+        {
+            Ok((-opponents_best_evaluation.deepen().relative_to(side), line))
+        } else {
+            let evaluation = self.evaluate(board)?;
+            Ok((-evaluation.relative_to(side), vec![]))
+        }
+    }
+
+    // How many plies it's been since the last capture or pawn move, once
+    // `chess_move` is made in `parent`. Passing starts the count over too,
+    // since the positions before it weren't actually reached by moves.
+    fn plies_since_reset(
+        &self,
+        parent: &Board,
+        chess_move: &ChessMove,
+    ) -> usize {
+        let resets = *chess_move == ChessMove::NullMove
+            || parent.get_captured_piece(chess_move).is_some()
+            || parent
+                .get_moving_piece(chess_move)
+                .is_some_and(|p| p.piece_type == PieceType::Pawn);
+
+        match self.line_positions.last() {
+            Some((_, plies)) if !resets => plies + 1,
+            _ => 0,
+        }
+    }
+
+    // Whether `board`, with `hash`, is a draw by repetition, the fifty-move
+    // rule or insufficient material, `plies_since_reset` plies after the last
+    // capture or pawn move
+    fn is_draw(
+        &self,
+        board: &Board,
+        hash: u64,
+        plies_since_reset: usize,
+    ) -> bool {
+        if plies_since_reset >= FIFTY_MOVE_PLIES
+            || board.has_insufficient_material()
+        {
+            return true;
+        }
+
+        // Positions from before the last capture or pawn move can't come up
+        // again, so there's no need to look further back
+        self.game_history
+            .iter()
+            .chain(self.line_positions.iter().map(|(h, _)| h))
+            .rev()
+            .take(plies_since_reset)
+            .any(|h| *h == hash)
+    }
+
+    // How a draw is scored, see `set_contempt`
+    fn draw_evaluation(&self) -> Evaluation {
+        match self.contempt {
+            Some(contempt) => (Evaluation::Estimate(0)
+                .relative_to(self.engine_side)
+                - contempt)
+                .absolute(),
+            None => Evaluation::Certain(ChessResult::Draw, 0),
+        }
+    }
+
+    // Evaluates `board` with the final position evaluator, scoring draws the
+    // way `set_contempt` says to
+    fn evaluate(&self, board: &Board) -> Result<Evaluation, &'static str> {
+        if board.has_insufficient_material() {
+            return Ok(self.draw_evaluation());
+        }
+
+        let evaluation = self.final_position_evaluator.evaluate(board)?;
+        Ok(match evaluation {
+            Evaluation::Certain(ChessResult::Draw, _)
+                if self.contempt.is_some() =>
+            {
+                self.draw_evaluation()
+            }
+            _ => evaluation,
+        })
+    }
+
+    // Whether every move in `board` other than `tt_move` is clearly worse
+    // than `tt_evaluation`, the evaluation `tt_move` had when `board` was
+    // last searched. The other moves are only searched half as deep.
+    fn is_singular(
+        &mut self,
+        board: &Board,
+        tt_move: &ChessMove,
+        depth: Depth,
+        tt_evaluation: RelativeEvaluation,
+    ) -> Result<bool, &'static str> {
+        let bound = tt_evaluation - SINGULAR_MARGIN;
+
+        for m in board.generate_moves(true)? {
+            if m == *tt_move {
+                continue;
+            }
+
+            let mut moved_board = board.clone();
+
+            // This can't fail, because it was generated by movegen
+            moved_board.make_move(m.clone(), false).unwrap();
+
+            let (evaluation, _) = self.search_child(
+                board,
+                &moved_board,
+                &m,
+                depth / 2,
+                bound,
+                bound,
+            )?;
+            if evaluation >= bound {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    // Scores moves by evaluating the positions after them with the move
+    // sorting evaluator. Moves that are evaluated the same get the same score.
+    fn score_by_evaluation(
+        &self,
+        side: Side,
+        moved_boards: impl Iterator<Item = (ChessMove, Board)>,
+    ) -> Vec<(ChessMove, Board, i32)> {
+        let mut evaluated_boards = moved_boards
+            .map(|(m, b)| {
+                let evaluation = self
+                    .move_sorting_evaluator
+                    .evaluate(&b)
+                    .ok()
+                    .map(|e| e.relative_to(side));
+                (m, b, evaluation)
+            })
+            .collect::<Vec<_>>();
+
+        // The moves best for the side to move go first, and the ones that
+        // couldn't be evaluated last
+        evaluated_boards
+            .sort_unstable_by_key(|(_, _, evaluation)| Reverse(*evaluation));
+
+        let mut score = 0;
+        let mut previous_evaluation = None;
+        (0..)
+            .zip(evaluated_boards)
+            .map(|(index, (m, b, evaluation))| {
+                if previous_evaluation != Some(evaluation) {
+                    score = -index;
+                    previous_evaluation = Some(evaluation);
+                }
+                (m, b, score)
+            })
+            .collect()
+    }
+
+    // Searches the root once for every MultiPV line, and returns the best
+    // move of every pass, followed by the moves from the last pass
+    fn search_root(
+        &mut self,
+        board: &Board,
+    ) -> Result<Vec<SearchedMove>, &'static str> {
+        let lines = self.multi_pv.min(board.generate_moves(true)?.len());
+        let mut best_moves = vec![];
+
+        loop {
+            // Only the first pass has an earlier evaluation to aim for
+            let mut searched_moves = if best_moves.is_empty() {
+                self.search_aspiration(board)?
+            } else {
+                let (alpha, beta) = relative_window(
+                    board.get_current_side(),
+                    Evaluation::BEST_FOR_BLACK, // worst result for white
+                    Evaluation::BEST_FOR_WHITE, // worst result for black
+                );
+                self.search_order_impl(board, self.depth, alpha, beta)?
+            };
+
+            // The best move of every pass has an exact evaluation, since
+            // nothing else at the root was good enough to cut it off
+            if best_moves.len() + 1 >= lines || searched_moves.len() <= 1 {
+                best_moves.append(&mut searched_moves);
+                return Ok(best_moves);
+            }
+
+            let best_move = searched_moves.remove(0);
+            self.excluded_root_moves.push(best_move.0.clone());
+            best_moves.push(best_move);
+        }
+    }
+
+    // Searches the root with a window around the last evaluation of the same
+    // position, widening it for as long as the evaluation falls outside it
+    fn search_aspiration(
+        &mut self,
+        board: &Board,
+    ) -> Result<Vec<SearchedMove>, &'static str> {
+        let mut alpha = Evaluation::BEST_FOR_BLACK;
+        let mut beta = Evaluation::BEST_FOR_WHITE;
+        let mut width = None;
+
+        if let (Some(Evaluation::Estimate(last)), Some(w)) =
+            (self.last_root_evaluation, self.aspiration_window)
+        {
+            alpha = Evaluation::Estimate(last.saturating_sub(w));
+            beta = Evaluation::Estimate(last.saturating_add(w));
+            width = Some(w);
+        }
+
+        loop {
+            let (relative_alpha, relative_beta) =
+                relative_window(board.get_current_side(), alpha, beta);
+            let searched_moves = self.search_order_impl(
+                board,
+                self.depth,
+                relative_alpha,
+                relative_beta,
+            )?;
+            let best = searched_moves[0].1;
+
+            // Evaluations strictly outside the window are only bounds, so we
+            // widen the window on that side and try again. The width doubles
+            // every time, and since mates can't be put in a window of
+            // centipawns, they open that side fully.
+            width = width.map(|w| w.saturating_mul(2));
+            if best < alpha {
+                alpha = match (best, width) {
+                    (Evaluation::Estimate(e), Some(w)) => {
+                        Evaluation::Estimate(e.saturating_sub(w))
+                    }
+                    _ => Evaluation::BEST_FOR_BLACK,
+                };
+            } else if best > beta {
+                beta = match (best, width) {
+                    (Evaluation::Estimate(e), Some(w)) => {
+                        Evaluation::Estimate(e.saturating_add(w))
+                    }
+                    _ => Evaluation::BEST_FOR_WHITE,
+                };
+            } else {
+                return Ok(searched_moves);
+            }
+        }
+    }
+
+    /// Evaluates `board` by only looking at captures until the position is
+    /// quiet. This stops the search from evaluating a position in the middle
+    /// of an exchange, like right after a queen takes a defended pawn.
+    ///
+    /// If `include_checks` is set, quiet moves that give check are also
+    /// looked at, but only at the first ply.
+    ///
+    /// `ply` counts how far past the search depth `board` is. Once that
+    /// reaches `MAX_QUIESCENCE_PLIES`, the position is evaluated as it
+    /// stands, even if the side to move is in check.
+    fn quiescence(
+        &mut self,
+        board: &Board,
+        mut alpha: RelativeEvaluation,
+        beta: RelativeEvaluation,
+        include_checks: bool,
+        ply: Depth,
+    ) -> Result<RelativeEvaluation, &'static str> {
+        self.check_limits()?;
+        self.stats.nodes += 1;
+        self.stats.quiescence_nodes += 1;
+        self.report_progress();
+
+        let side = board.get_current_side();
+        if ply >= MAX_QUIESCENCE_PLIES {
+            return Ok(self
+                .final_position_evaluator
+                .evaluate(board)?
+                .relative_to(side));
+        }
+
+        let mut best = None;
+
+        // When in check, doing nothing isn't an option, so we have to look at
+        // every move that gets us out of it
+        let mut moves = if board.check_king_threat()? {
+            board.generate_moves(true)?
+        } else {
+            // The side to move doesn't have to capture anything, so the
+            // position is at least as good for them as it is right now
+            // ("standing pat")
+            let stand_pat = self.evaluate(board)?.relative_to(side);
+            if let Evaluation::Certain(_, _) = stand_pat.absolute() {
+                return Ok(stand_pat);
+            }
+
+            if stand_pat > beta {
+                return Ok(stand_pat);
+            } else if stand_pat > alpha {
+                alpha = stand_pat;
+            }
+            best = Some(stand_pat);
+
+            // Captures that lose material according to static exchange
+            // evaluation are very unlikely to help, so we skip them
+            let mut moves: Vec<_> = board
+                .generate_moves_with_mode(true, GenerationMode::Captures)?
+                .into_iter()
+                .filter(|m| board.see_with_values(m, &self.piece_values) >= 0)
+                .collect();
+            if include_checks {
+                moves.append(
+                    &mut board.generate_moves_with_mode(
+                        true,
+                        GenerationMode::Checks,
+                    )?,
+                );
+            }
+            moves
+        };
+
+        if moves.is_empty() {
+            return match best {
+                Some(evaluation) => Ok(evaluation),
+                None => Ok(self.evaluate(board)?.relative_to(side)),
+            };
+        }
+
+        // Look at the most valuable victims first, and out of those, capture
+        // with the least valuable attacker first (MVV-LVA)
+        let values = &self.piece_values;
+        moves.sort_by_key(|m| {
+            let victim = board
+                .get_captured_piece(m)
+                .map_or(0, |p| values.get(p.piece_type));
+            let attacker = board
+                .get_moving_piece(m)
+                .map_or(0, |p| values.get(p.piece_type));
+            (-victim, attacker)
+        });
+
+        for m in moves {
+            let mut moved_board = board.clone();
+
+            // This can't fail, because it was generated by movegen
+            moved_board.make_move(m, false).unwrap();
+
+            // The window is the other way around for the opponent, and mates
+            // are a ply closer for them
+            let evaluation = -self
+                .quiescence(
+                    &moved_board,
+                    -beta.undeepen(),
+                    -alpha.undeepen(),
+                    false,
+                    ply + 1,
+                )?
+                .deepen();
+
+            best = Some(best.map_or(evaluation, |b| b.max(evaluation)));
+
+            if evaluation > beta {
+                break;
+            } else if evaluation > alpha {
+                alpha = evaluation;
+            }
+        }
+
+        // There was at least one move, so this was set in the loop
+        Ok(best.unwrap())
+    }
+}
+
+/// The window `alpha..=beta` from `side`'s point of view, see
+/// `AlphabetaSearch::search_order_impl`
+fn relative_window(
+    side: Side,
+    alpha: Evaluation,
+    beta: Evaluation,
+) -> (RelativeEvaluation, RelativeEvaluation) {
+    let (alpha, beta) = (alpha.relative_to(side), beta.relative_to(side));
+    (alpha.min(beta), alpha.max(beta))
+}
+
+/// Total value of the pieces `side` has on `board`, other than pawns and the
+/// king
+fn get_non_pawn_material(
+    board: &Board,
+    side: Side,
+    values: &PieceValues,
+) -> Centipawns {
+    board
+        .get_squares()
+        .iter()
+        .flatten()
+        .filter(|p| p.side == side)
+        .filter(|p| !matches!(p.piece_type, PieceType::Pawn | PieceType::King))
+        .map(|p| values.get(p.piece_type))
+        .sum()
+}
+
+impl<ME, PE> Searcher for AlphabetaSearch<ME, PE>
+where
+    PE: Evaluator,
+    ME: Evaluator,
+{
+    /// Like the default, but when the search is stopped before it's done,
+    /// returns the best move it found so far, if it got as far as finding
+    /// one. Its evaluation can be a bound rather than exact.
+    fn search(
+        &mut self,
+        board: &Board,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        match self.search_order(board) {
+            Ok(order) => order.first().cloned().ok_or("No moves possible"),
+            Err(SEARCH_ABORTED) => {
+                self.best_root_move.clone().ok_or(SEARCH_ABORTED)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn search_order(
+        &mut self,
+        board: &Board,
+    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
+        self.start_search(board);
+        let result = self.search_root(board);
+        self.excluded_root_moves.clear();
+        self.stats.elapsed = self.search_start.elapsed();
+        let searched_moves = result?;
+        self.last_root_evaluation = searched_moves.first().map(|(_, e, _)| *e);
+
+        let (order, principal_variations) = searched_moves
+            .into_iter()
+            .map(|(m, evaluation, line)| ((m, evaluation), line))
+            .unzip();
+        self.principal_variations = principal_variations;
+
+        Ok(order)
+    }
+
+    fn set_limits(&mut self, limits: &SearchLimits) {
+        self.depth = limits.max_depth();
+        self.limits = limits.clone();
+    }
+
+    fn set_multi_pv(&mut self, lines: usize) {
+        AlphabetaSearch::set_multi_pv(self, lines);
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.observer = observer;
+    }
+
+    fn observer(&mut self) -> Option<&mut dyn SearchObserver> {
+        Some(self.observer.as_mut())
+    }
+
+    fn set_root_move_order(&mut self, moves: Vec<ChessMove>) {
+        self.root_move_order = moves;
+    }
+
+    fn search_stats(&self) -> SearchStats {
+        self.stats.clone()
+    }
+
+    fn get_principal_variation(&self, root_move: &ChessMove) -> Vec<ChessMove> {
+        self.principal_variations
+            .iter()
+            .find(|line| line.first() == Some(root_move))
+            .cloned()
+            .unwrap_or_else(|| vec![root_move.clone()])
+    }
+
+    fn new_game(&mut self) {
+        self.transposition_table.clear();
+        self.move_orderer.clear();
+        self.last_root = None;
+        self.last_root_evaluation = None;
+        self.principal_variations.clear();
+        self.game_history.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use std::time::Duration;
+
+    use crate::board::game::ChessResult;
+    use crate::board::square::Square;
+    use crate::evaluation::composite::CompositeEvaluator;
+    use crate::evaluation::material::MaterialEvaluator;
+    use crate::evaluation::result::ResultEvaluator;
+    use crate::search::stop::StopHandle;
+    use crate::search::test_utils::{
+        get_mate_in_four_board, get_mate_in_two_board, get_mate_searcher,
+        get_material_searcher, get_queen_against_pawn_board, get_rook_up_board,
+        get_tactical_board,
+    };
+    use crate::search::{move_ordering::MoveOrdering, time_manager::Deadlines};
+
+    use super::*;
+
+    #[test]
+    fn finds_mate_in_two() {
+        let board = get_mate_in_two_board();
+
+        let mut searcher = get_mate_searcher();
+        searcher.depth = 4;
+
+        let (_, evaluation) = searcher.search(&board).unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
+        );
+    }
+
+    #[test]
+    fn principal_variation_ends_in_mate() {
+        let mut board = get_mate_in_two_board();
+
+        let mut searcher = get_mate_searcher();
+        searcher.depth = 4;
+
+        let (pv, _) = searcher.search_principal_variation(&board).unwrap();
+        assert_eq!(pv.len(), 3);
+
+        for m in pv {
+            board.make_move(m, true).unwrap();
+        }
+        assert_eq!(
+            board.get_game_result().unwrap(),
+            Some(ChessResult::Checkmate(Side::Black))
+        );
+    }
+
+    #[test]
+    fn every_root_move_has_a_principal_variation() {
+        let mut searcher = get_material_searcher();
+        searcher.depth = 3;
+
+        let order = searcher.search_order(&Board::default()).unwrap();
+        for (m, _) in order {
+            let pv = searcher.get_principal_variation(&m);
+            assert_eq!(pv[0], m);
+            assert!(pv.len() <= 3);
+        }
+    }
+
+    #[test]
+    fn collects_search_stats() {
+        let mut searcher = get_material_searcher();
+        searcher.depth = 3;
+
+        // Extensions would let some lines go deeper than the search depth
+        searcher
+            .set_check_extensions(false)
+            .set_one_reply_extensions(false)
+            .set_singular_extensions(false);
+
+        let (_, _, stats) =
+            searcher.search_with_stats(&Board::default()).unwrap();
+        assert_eq!(stats.nodes_per_ply.len(), 3);
+        assert_eq!(stats.nodes_per_ply[0], 1);
+        assert_eq!(
+            stats.nodes,
+            stats.nodes_per_ply.iter().sum::<u64>() + stats.quiescence_nodes
+        );
+        assert!(stats.beta_cutoffs > 0);
+        assert!(stats.first_move_beta_cutoffs <= stats.beta_cutoffs);
+
+        // Searching the same position again should find it in the table
+        let (_, _, stats) =
+            searcher.search_with_stats(&Board::default()).unwrap();
+        assert!(stats.tt_hits > 0);
+        assert!(stats.tt_cutoffs > 0);
+    }
+
+    #[test]
+    fn gives_up_after_node_limit() {
+        let mut searcher = get_material_searcher();
+        searcher.set_limits(&SearchLimits {
+            nodes: Some(100),
+            ..SearchLimits::depth(4)
+        });
+
+        assert_eq!(searcher.search(&Board::default()), Err(SEARCH_ABORTED));
+        assert_eq!(searcher.nodes_searched(), 100);
+    }
+
+    #[test]
+    fn returns_best_move_so_far_when_stopped() {
+        let mut searcher = get_material_searcher();
+        let board = Board::default();
+
+        searcher.search(&board).unwrap();
+        let nodes = searcher.nodes_searched();
+        searcher.new_game();
+
+        // Running out of nodes halfway through stops the search the same way
+        // a stop handle does, with some of the root moves searched
+        searcher.set_limits(&SearchLimits {
+            nodes: Some(nodes / 2),
+            ..SearchLimits::depth(4)
+        });
+        let (best_move, _) = searcher.search(&board).unwrap();
+        assert_eq!(searcher.nodes_searched(), nodes / 2);
+        assert!(board.generate_moves(true).unwrap().contains(&best_move));
+
+        // The search didn't get to rank the moves, so there's no order
+        assert_eq!(searcher.search_order(&board), Err(SEARCH_ABORTED));
+    }
+
+    #[test]
+    fn gives_up_when_stopped_before_finding_a_move() {
+        let mut searcher = get_material_searcher();
+        let stop = StopHandle::new();
+        searcher.set_limits(&SearchLimits {
+            stop: Some(stop.clone()),
+            ..SearchLimits::depth(4)
+        });
+        stop.stop();
+
+        assert_eq!(searcher.search(&Board::default()), Err(SEARCH_ABORTED));
+        assert_eq!(searcher.nodes_searched(), 0);
+
+        stop.reset();
+        assert!(searcher.search(&Board::default()).is_ok());
+    }
+
+    #[test]
+    fn finds_mate_in_four() {
+        let board = get_mate_in_four_board();
+
+        let mse = MaterialEvaluator::new();
+
+        let re2 = ResultEvaluator::new();
+        let me2 = MaterialEvaluator::new();
+        let mut fpe = CompositeEvaluator::new();
+        fpe.push(Box::new(re2)).push(Box::new(me2));
+
+        let mut searcher = AlphabetaSearch::new(mse, fpe);
+        searcher.depth = 8;
+
+        let (_, evaluation) = searcher.search(&board).unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 7)
+        );
+    }
+
+    #[test]
+    fn gives_up_after_deadline() {
+        let mut searcher = get_material_searcher();
+        searcher.set_limits(&SearchLimits {
+            move_time: Some(Duration::ZERO),
+            ..SearchLimits::depth(4)
+        });
+
+        assert_eq!(searcher.search(&Board::default()), Err(SEARCH_ABORTED));
+    }
+
+    #[test]
+    fn timed_search_finds_mate() {
+        let board = get_mate_in_two_board();
+
+        let mut searcher = get_mate_searcher();
+
+        let now = Instant::now();
+        let deadlines = Deadlines {
+            soft: now + Duration::from_secs(60),
+            hard: now + Duration::from_secs(120),
+        };
+
+        let (_, evaluation) = searcher.search_timed(&board, deadlines).unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
+        );
+    }
+
+    #[test]
+    fn timed_search_always_returns_a_move() {
+        let board = Board::default();
+        let mut searcher = get_material_searcher();
+
+        let now = Instant::now();
+        let deadlines = Deadlines {
+            soft: now,
+            hard: now,
+        };
+
+        let (chess_move, _) = searcher.search_timed(&board, deadlines).unwrap();
+        assert!(board.generate_moves(true).unwrap().contains(&chess_move));
+    }
+
+    #[test]
+    fn reuses_earlier_results() {
+        let board = get_queen_against_pawn_board();
+
+        let mut searcher = get_material_searcher();
+        searcher.set_depth(3);
+
+        let (_, first_evaluation) = searcher.search(&board).unwrap();
+        let first_nodes = searcher.nodes_searched();
+
+        let (_, second_evaluation) = searcher.search(&board).unwrap();
+        assert_eq!(first_evaluation, second_evaluation);
+        assert!(searcher.nodes_searched() < first_nodes);
+
+        searcher.new_game();
+        searcher.search(&board).unwrap();
+        assert_eq!(searcher.nodes_searched(), first_nodes);
+    }
+
+    #[test]
+    fn doesnt_take_defended_pawn_with_queen() {
+        let board = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ....p...\n\
+             ...p....\n\
+             ...Q....\n\
+             ........\n\
+             ........\n\
+             ....K...\n",
+        )
+        .unwrap();
+
+        let mut searcher = get_material_searcher();
+        searcher.set_depth(1);
+
+        let (chess_move, evaluation) = searcher.search(&board).unwrap();
+        assert_ne!(chess_move, "d4d5".try_into().unwrap());
+        assert_eq!(evaluation, Evaluation::Estimate(700));
+    }
+
+    #[test]
+    fn multi_pv_gives_exact_evaluations() {
+        let board = get_tactical_board();
+
+        let mut searcher = get_material_searcher();
+        searcher.set_multi_pv(3);
+        searcher.set_depth(2);
+
+        // The lines are compared with searches of the positions after each
+        // move, which would be extended differently
+        searcher
+            .set_check_extensions(false)
+            .set_one_reply_extensions(false)
+            .set_singular_extensions(false);
+
+        let order = searcher.search_order(&board).unwrap();
+        assert_eq!(order.len(), board.generate_moves(true).unwrap().len());
+
+        let lines = &order[..3];
+        for pair in lines.windows(2) {
+            assert!(pair[0].1 >= pair[1].1);
+        }
+
+        // Searching the position after each move on its own should give the
+        // same evaluation, since none of them were cut off
+        for (chess_move, evaluation) in lines {
+            let mut moved_board = board.clone();
+            moved_board.make_move(chess_move.clone(), true).unwrap();
+
+            let mut single = get_material_searcher();
+            single
+                .set_check_extensions(false)
+                .set_one_reply_extensions(false)
+                .set_singular_extensions(false);
+            single.set_depth(1);
+            let (_, expected) = single.search(&moved_board).unwrap();
+
+            assert_eq!(*evaluation, expected.deepen());
+            assert_eq!(searcher.get_principal_variation(chess_move).len(), 2);
+        }
+    }
+
+    // Material, plus a small bonus for pieces near the center. With material
+    // alone most positions are evaluated exactly the same, and cutoffs only
+    // happen on evaluations that are strictly better, so there's little for
+    // null windows to cut off. Checkmates are evaluated as such, but to keep
+    // tests fast, stalemates aren't.
+    struct CentralizingEvaluator;
+
+    impl Evaluator for CentralizingEvaluator {
+        fn evaluate(&self, board: &Board) -> Result<Evaluation, &'static str> {
+            if board.check_king_threat()? {
+                let result = ResultEvaluator::new().evaluate(board)?;
+                if let Evaluation::Certain(..) = result {
+                    return Ok(result);
+                }
+            }
+
+            let material = MaterialEvaluator::new().evaluate(board)?;
+            let centralization: i32 = board
+                .get_squares()
+                .iter()
+                .enumerate()
+                .filter_map(|(index, piece)| {
+                    let piece = (*piece)?;
+                    let file = (index % 8) as i32;
+                    let rank = (index / 8) as i32;
+                    let bonus =
+                        14 - (2 * file - 7).abs() - (2 * rank - 7).abs();
+                    Some(match piece.side {
+                        Side::White => bonus,
+                        Side::Black => -bonus,
+                    })
+                })
+                .sum();
+
+            Ok(match material {
+                Evaluation::Estimate(c) => {
+                    Evaluation::Estimate(c + centralization)
+                }
+                certain => certain,
+            })
+        }
+    }
+
+    #[test]
+    fn pvs_and_aspiration_find_same_moves_with_fewer_nodes() {
+        let boards = [
+            Board::default(),
+            get_tactical_board(),
+            Board::from_art(
+                "....k...\n\
+                 ppp..ppp\n\
+                 ..n.....\n\
+                 ...p....\n\
+                 ...P.B..\n\
+                 ..N.....\n\
+                 PPP..PPP\n\
+                 ....K...\n",
+            )
+            .unwrap(),
+        ];
+
+        let search = |board: &Board, enhanced: bool| {
+            let mut searcher = AlphabetaSearch::new(
+                CentralizingEvaluator,
+                CentralizingEvaluator,
+            );
+            searcher
+                .set_principal_variation_search(enhanced)
+                .set_aspiration_window(enhanced.then_some(50));
+
+            let limits = SearchLimits::depth(4);
+            let result = searcher.search_with_limits(board, &limits).unwrap();
+            (result, searcher.nodes_searched())
+        };
+
+        let mut plain_nodes = 0;
+        let mut enhanced_nodes = 0;
+        for board in &boards {
+            let (plain_result, nodes) = search(board, false);
+            plain_nodes += nodes;
+            let (enhanced_result, nodes) = search(board, true);
+            enhanced_nodes += nodes;
+
+            assert_eq!(enhanced_result, plain_result);
+        }
+
+        assert!(enhanced_nodes < plain_nodes);
+    }
+
+    #[test]
+    fn null_move_pruning_searches_fewer_nodes() {
+        // White is a rook up, so Black's replies often aren't worth a full
+        // search
+        let board = get_rook_up_board();
+
+        let search = |null_move_pruning: bool| {
+            let mut searcher = AlphabetaSearch::new(
+                CentralizingEvaluator,
+                CentralizingEvaluator,
+            );
+            searcher
+                .set_null_move_pruning(null_move_pruning)
+                .set_futility_pruning(false)
+                .set_reverse_futility_pruning(false)
+                .set_late_move_reductions(false);
+
+            let limits = SearchLimits::depth(5);
+            searcher.search_with_limits(&board, &limits).unwrap();
+            searcher.search_stats()
+        };
+
+        let plain = search(false);
+        let pruned = search(true);
+
+        assert_eq!(plain.null_move_cutoffs, 0);
+        assert!(pruned.null_move_cutoffs > 0);
+        assert!(pruned.nodes < plain.nodes);
+    }
+
+    #[test]
+    fn heuristic_move_ordering_searches_fewer_nodes() {
+        let boards =
+            [Board::default(), get_tactical_board(), get_rook_up_board()];
+
+        let search = |board: &Board, ordering: MoveOrdering| {
+            let mut searcher = get_material_searcher();
+            searcher.set_move_ordering(ordering);
+
+            let limits = SearchLimits::depth(4);
+            let (_, evaluation) =
+                searcher.search_with_limits(board, &limits).unwrap();
+            (evaluation, searcher.nodes_searched())
+        };
+
+        let mut evaluator_nodes = 0;
+        let mut heuristic_nodes = 0;
+        for board in &boards {
+            let (evaluator_evaluation, nodes) =
+                search(board, MoveOrdering::Evaluator);
+            evaluator_nodes += nodes;
+            let (heuristic_evaluation, nodes) =
+                search(board, MoveOrdering::Heuristics);
+            heuristic_nodes += nodes;
+
+            assert_eq!(heuristic_evaluation, evaluator_evaluation);
+        }
+
+        assert!(heuristic_nodes < evaluator_nodes);
+    }
+
+    #[test]
+    fn searches_boards_of_other_sizes() {
+        let board = Board::from_art(
+            "....k.....\n\
+             ..n.......\n\
+             ..........\n\
+             .......r..\n\
+             ....q.....\n\
+             ..........\n\
+             .N........\n\
+             ....R.....\n\
+             ..........\n\
+             ....K.....\n",
+        )
+        .unwrap();
+
+        let mut searcher = get_material_searcher();
+        let (best_move, _) = searcher
+            .search_with_limits(&board, &SearchLimits::depth(4))
+            .unwrap();
+
+        let takes_queen = ChessMove::SimpleMove(
+            Square { file: 4, rank: 2 },
+            Square { file: 4, rank: 5 },
+        );
+        assert_eq!(best_move, takes_queen);
+
+        // Every move of the expected line has to be playable in turn
+        let principal_variation = searcher.get_principal_variation(&best_move);
+        assert!(principal_variation.len() > 1);
+
+        let mut position = board;
+        for m in principal_variation {
+            assert!(position.generate_moves(true).unwrap().contains(&m));
+            position.make_move(m, true).unwrap();
+        }
+    }
+
+    // Options for `AlphabetaSearch` that prune the search tree, along with
+    // the counter in `SearchStats` that shows them doing anything
+    type PruningOption = (
+        fn(
+            &mut AlphabetaSearch<CentralizingEvaluator, CentralizingEvaluator>,
+            bool,
+        ),
+        fn(&SearchStats) -> u64,
+    );
+
+    const PRUNING_OPTIONS: [PruningOption; 3] = [
+        (
+            |s, enabled| {
+                s.set_futility_pruning(enabled);
+            },
+            |stats| stats.futility_pruned_moves,
+        ),
+        (
+            |s, enabled| {
+                s.set_reverse_futility_pruning(enabled);
+            },
+            |stats| stats.reverse_futility_cutoffs,
+        ),
+        (
+            |s, enabled| {
+                s.set_late_move_reductions(enabled);
+            },
+            |stats| stats.reduced_moves,
+        ),
+    ];
+
+    #[test]
+    fn pruning_options_search_fewer_nodes() {
+        let board = get_rook_up_board();
+
+        let search = |option: Option<&PruningOption>| {
+            let mut searcher = AlphabetaSearch::new(
+                CentralizingEvaluator,
+                CentralizingEvaluator,
+            );
+            searcher.set_null_move_pruning(false);
+            for (set_option, _) in &PRUNING_OPTIONS {
+                set_option(&mut searcher, false);
+            }
+            if let Some((set_option, _)) = option {
+                set_option(&mut searcher, true);
+            }
+
+            let limits = SearchLimits::depth(5);
+            searcher.search_with_limits(&board, &limits).unwrap();
+            searcher.search_stats()
+        };
+
+        let plain = search(None);
+        for option in &PRUNING_OPTIONS {
+            let (_, pruned) = option;
+            assert_eq!(pruned(&plain), 0);
+
+            let stats = search(Some(option));
+            assert!(pruned(&stats) > 0);
+            assert!(stats.nodes < plain.nodes);
+        }
+    }
+
+    #[test]
+    fn pruning_options_still_find_mates() {
+        // Mate in two, and mate in four
+        let boards = [get_mate_in_two_board(), get_mate_in_four_board()];
+        let mates = [3, 7];
+
+        for (board, mate) in boards.iter().zip(mates) {
+            for (set_option, _) in &PRUNING_OPTIONS {
+                let mut searcher = AlphabetaSearch::new(
+                    CentralizingEvaluator,
+                    CentralizingEvaluator,
+                );
+                for (other_option, _) in &PRUNING_OPTIONS {
+                    other_option(&mut searcher, false);
+                }
+                set_option(&mut searcher, true);
+                searcher.set_depth(mate + 1);
+
+                let (_, evaluation) = searcher.search(board).unwrap();
+                assert_eq!(
+                    evaluation,
+                    Evaluation::Certain(
+                        ChessResult::Checkmate(Side::Black),
+                        mate
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn no_null_moves_with_only_pawns() {
+        // Whoever has to move here loses their pawn, so passing would make
+        // the position look better than it is
+        let board = Board::from_art(
+            "........\n\
+             ........\n\
+             ........\n\
+             ..kpK...\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n",
+        )
+        .unwrap();
+
+        let mut searcher =
+            AlphabetaSearch::new(CentralizingEvaluator, CentralizingEvaluator);
+        searcher.set_depth(5);
+        searcher.search(&board).unwrap();
+
+        assert_eq!(searcher.search_stats().null_move_cutoffs, 0);
+    }
+
+    #[test]
+    fn null_move_verification_keeps_results() {
+        let board = get_rook_up_board();
+
+        let search = |verification: bool| {
+            let mut searcher = AlphabetaSearch::new(
+                CentralizingEvaluator,
+                CentralizingEvaluator,
+            );
+            searcher.set_null_move_verification(verification);
+
+            let limits = SearchLimits::depth(4);
+            let result = searcher.search_with_limits(&board, &limits).unwrap();
+            (result, searcher.search_stats())
+        };
+
+        let (plain_result, plain) = search(false);
+        let (verified_result, verified) = search(true);
+        assert_eq!(verified_result, plain_result);
+        assert!(verified.null_move_cutoffs <= plain.null_move_cutoffs);
+    }
+
+    #[test]
+    fn finds_mate_in_two_with_quiescence_checks() {
+        let board = get_mate_in_two_board();
+
+        let mut searcher = get_mate_searcher();
+        searcher.set_quiescence_checks(true);
+        searcher.set_depth(2);
+
+        let (_, evaluation) = searcher.search(&board).unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
+        );
+    }
+
+    #[test]
+    fn quiescence_stops_at_its_ply_limit() {
+        // White is in check, and gets out of it by taking the rook. Black's
+        // pawn keeps that from being a draw by insufficient material.
+        let board = Board::from_art(
+            "p...k...\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ....r...\n\
+             ....K...\n",
+        )
+        .unwrap();
+
+        let mut searcher = get_material_searcher();
+        let mut quiescence = |ply| {
+            let (alpha, beta) = relative_window(
+                Side::White,
+                Evaluation::BEST_FOR_BLACK,
+                Evaluation::BEST_FOR_WHITE,
+            );
+            searcher
+                .quiescence(&board, alpha, beta, false, ply)
+                .unwrap()
+                .absolute()
+        };
+
+        assert_eq!(quiescence(0), Evaluation::Estimate(-100));
+        assert_eq!(
+            quiescence(MAX_QUIESCENCE_PLIES),
+            Evaluation::Estimate(-600)
+        );
+    }
+
+    #[test]
+    fn ignores_illegal_transposition_table_moves() {
+        let board = Board::default();
+        let mut searcher = get_material_searcher();
+        searcher.depth = 2;
+
+        // Pretend a different position with the same hash was stored, with a
+        // move that can't be made here
+        let illegal_move: ChessMove = "a1a6".try_into().unwrap();
+        assert!(!board.generate_moves(true).unwrap().contains(&illegal_move));
+        searcher.transposition_table.store(
+            board.get_hash(),
+            1,
+            Bound::Exact,
+            Evaluation::Estimate(1000),
+            Some(illegal_move.clone()),
+        );
+
+        let (alpha, beta) = relative_window(
+            Side::White,
+            Evaluation::BEST_FOR_BLACK,
+            Evaluation::BEST_FOR_WHITE,
+        );
+        let result =
+            searcher.search_order_impl(&board, 1, alpha, beta).unwrap();
+        assert_ne!(result[0].0, illegal_move);
+        assert_ne!(result[0].1, Evaluation::Estimate(1000));
+    }
+
+    #[test]
+    fn check_extensions_find_mates_past_the_search_depth() {
+        // Mate in three, with a check on every move
+        let board = Board::from_art(
+            "...r.b.k\n\
+             ......pp\n\
+             ........\n\
+             ......N.\n\
+             ..Q.....\n\
+             ........\n\
+             ........\n\
+             ......K.\n",
+        )
+        .unwrap();
+
+        let search = |extensions: bool| {
+            let mut searcher = AlphabetaSearch::new(
+                CentralizingEvaluator,
+                CentralizingEvaluator,
+            );
+            searcher
+                .set_check_extensions(extensions)
+                .set_one_reply_extensions(extensions)
+                .set_singular_extensions(extensions);
+            searcher.set_depth(4);
+
+            let (_, evaluation) = searcher.search(&board).unwrap();
+            (evaluation, searcher.search_stats())
+        };
+
+        let (plain_evaluation, plain) = search(false);
+        assert!(matches!(plain_evaluation, Evaluation::Estimate(_)));
+        assert_eq!(plain.extensions, 0);
+
+        let (evaluation, extended) = search(true);
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 5)
+        );
+        assert!(extended.extensions > 0);
+
+        // Extensions along a line never add up to more than the depth
+        assert!(extended.nodes_per_ply.len() <= 8);
+    }
+
+    // The position in `art` with the colours swapped and the board turned
+    // around, so that it's Black to move in the same situation
+    fn mirrored_board(art: &str) -> Board {
+        let art: String = art
+            .lines()
+            .rev()
+            .flat_map(|line| line.chars().chain(Some('\n')))
+            .map(|c| {
+                if c.is_ascii_uppercase() {
+                    c.to_ascii_lowercase()
+                } else {
+                    c.to_ascii_uppercase()
+                }
+            })
+            .collect();
+
+        let mut board = Board::from_art(&art).unwrap();
+        board.flip_current_side();
+        board
+    }
+
+    fn mirrored_move(chess_move: &ChessMove) -> ChessMove {
+        let mirror = |s: Square| Square {
+            file: s.file,
+            rank: 7 - s.rank,
+        };
+        match *chess_move {
+            ChessMove::SimpleMove(from, to) => {
+                ChessMove::SimpleMove(mirror(from), mirror(to))
+            }
+            ChessMove::EnPassant(from, to, captured) => {
+                ChessMove::EnPassant(mirror(from), mirror(to), mirror(captured))
+            }
+            ref other => other.clone(),
+        }
+    }
+
+    fn mirrored_evaluation(evaluation: Evaluation) -> Evaluation {
+        match evaluation {
+            Evaluation::Estimate(c) => Evaluation::Estimate(-c),
+            Evaluation::Certain(ChessResult::Checkmate(side), plies) => {
+                Evaluation::Certain(ChessResult::Checkmate(side.flip()), plies)
+            }
+            draw => draw,
+        }
+    }
+
+    #[test]
+    fn mirrored_boards_give_mirrored_results() {
+        // A middlegame, a position where material can be won, and a mate in
+        // four
+        let arts = [
+            "r...k..r\n\
+             ppp..ppp\n\
+             ..n.bn..\n\
+             ...pp...\n\
+             ...PP...\n\
+             ..N.BN..\n\
+             PPP..PPP\n\
+             R...K..R\n",
+            "r...k...\n\
+             ..p.....\n\
+             ....n...\n\
+             ...p....\n\
+             .B.Q....\n\
+             ........\n\
+             .....PPP\n\
+             ......K.\n",
+            "..N.....\n\
+             ........\n\
+             k..K....\n\
+             ........\n\
+             p.PB....\n\
+             P.......\n\
+             ........\n\
+             ........\n",
+        ];
+        let depths = [4, 4, 8];
+
+        for (art, depth) in arts.iter().zip(depths) {
+            let search = |board: &Board| {
+                let mut searcher = AlphabetaSearch::new(
+                    CentralizingEvaluator,
+                    CentralizingEvaluator,
+                );
+                searcher.set_depth(depth);
+                searcher.search(board).unwrap()
+            };
+
+            let (best_move, evaluation) =
+                search(&Board::from_art(art).unwrap());
+            let (mirrored_best_move, mirrored) = search(&mirrored_board(art));
+
+            assert_eq!(mirrored, mirrored_evaluation(evaluation));
+            assert_eq!(mirrored_best_move, mirrored_move(&best_move));
+        }
+    }
+
+    #[test]
+    fn repeats_positions_only_when_contempt_permits() {
+        // White is a queen down, so a draw by going back to g1 is the best it
+        // can hope for, unless it thinks even less of a draw
+        let board = Board::from_art(
+            "k.......\n\
+             ........\n\
+             ........\n\
+             .q......\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             .......K\n",
+        )
+        .unwrap();
+        let repetition: ChessMove = "h1g1".try_into().unwrap();
+        let mut repeated = board.clone();
+        repeated.make_move(repetition.clone(), true).unwrap();
+
+        let search = |contempt| {
+            let mut searcher = get_material_searcher();
+            searcher
+                .set_contempt(contempt)
+                .set_game_history(vec![repeated.get_hash()])
+                .set_depth(3);
+            searcher.search(&board).unwrap()
+        };
+
+        assert_eq!(search(None).0, repetition);
+        assert_eq!(
+            search(Some(0)),
+            (repetition.clone(), Evaluation::Estimate(0))
+        );
+        assert_eq!(
+            search(Some(-100)),
+            (repetition.clone(), Evaluation::Estimate(100))
+        );
+        assert_ne!(search(Some(2000)).0, repetition);
+    }
+
+    #[test]
+    fn scores_fifty_move_draws_with_contempt() {
+        let board = Board::from_art(
+            "k.......\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             P.......\n\
+             .R.....K\n",
+        )
+        .unwrap();
+        let pawn_moves: Vec<ChessMove> =
+            vec!["a2a3".try_into().unwrap(), "a2a4".try_into().unwrap()];
+
+        // Any move but a pawn move is the hundredth without one
+        let search = |contempt| {
+            let mut searcher = get_material_searcher();
+            searcher
+                .set_contempt(Some(contempt))
+                .set_game_history((1..100).collect())
+                .set_depth(3);
+            searcher.search(&board).unwrap()
+        };
+
+        // Being ahead, White only takes the draw if it's told to seek them
+        assert!(pawn_moves.contains(&search(0).0));
+        assert_eq!(search(-2000).1, Evaluation::Estimate(2000));
     }
 }
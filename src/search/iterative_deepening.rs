@@ -0,0 +1,557 @@
+use std::{
+    ops::ControlFlow,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::{chess_move::ChessMove, game::ChessResult, Board},
+    evaluation::evaluation_result::{Depth, Evaluation},
+};
+
+use super::{
+    limits::SearchLimits, observer::SearchObserver, stats::SearchStats,
+    Searcher, SEARCH_ABORTED,
+};
+
+/// A summary of a completed iteration of iterative deepening
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IterationInfo {
+    /// How many plies this iteration looked ahead
+    pub depth: Depth,
+
+    /// Evaluation of the best move found in this iteration
+    pub evaluation: Evaluation,
+
+    /// The line of play expected after this iteration, starting with the best
+    /// move
+    pub principal_variation: Vec<ChessMove>,
+
+    /// What the search did over all iterations so far
+    pub stats: SearchStats,
+
+    /// Time since the first iteration started
+    pub elapsed: Duration,
+}
+
+/// Searches with `searcher` at depth 1, 2, 3 and so on until one of `limits`
+/// is reached, and returns the move order found by the last completed
+/// iteration.
+///
+/// After every iteration the root moves are handed back to the searcher in the
+/// order that was found, so that the next, more expensive, iteration looks at
+/// the most promising moves first.
+///
+/// Tells the searcher's observer about every completed iteration, just
+/// before calling `on_iteration`.
+///
+/// Stops between iterations when `on_iteration` returns `ControlFlow::Break`,
+/// when the soft deadline passes, or once a forced mate has been found. Stops
+/// within an iteration when the hard deadline passes, the node limit is
+/// reached or the stop handle is stopped, in which case that iteration is
+/// thrown away. The first iteration ignores the time and node limits and the
+/// stop handle, so that there's always a move to play.
+pub fn deepen<S, F>(
+    searcher: &mut S,
+    board: &Board,
+    limits: &SearchLimits,
+    mut on_iteration: F,
+) -> Result<Vec<(ChessMove, Evaluation)>, &'static str>
+where
+    S: Searcher + ?Sized,
+    F: FnMut(&IterationInfo) -> ControlFlow<()>,
+{
+    let start = Instant::now();
+    let deadlines = limits.get_deadlines(start);
+    let mut stats = SearchStats::default();
+    let mut best_order = vec![];
+    let mut last_depth = 1;
+
+    // Any order left over from an earlier search is for a different position
+    searcher.set_root_move_order(vec![]);
+
+    for depth in 1..=limits.max_depth() {
+        let mut iteration_limits = SearchLimits::depth(depth);
+        if depth > 1 {
+            let stopped = limits.stop.as_ref().is_some_and(|s| s.is_stopped());
+            if stopped || deadlines.is_some_and(|d| Instant::now() >= d.soft) {
+                break;
+            }
+            iteration_limits.deadlines = deadlines;
+            iteration_limits.stop = limits.stop.clone();
+
+            if let Some(node_limit) = limits.nodes {
+                if stats.nodes >= node_limit {
+                    break;
+                }
+                iteration_limits.nodes = Some(node_limit - stats.nodes);
+            }
+        }
+        searcher.set_limits(&iteration_limits);
+        last_depth = depth;
+
+        let result = searcher.search_order(board);
+        stats.merge(&searcher.search_stats());
+
+        let order = match result {
+            Ok(order) => order,
+            Err(SEARCH_ABORTED) => break,
+            Err(e) => {
+                reset_limits(searcher, depth);
+                return Err(e);
+            }
+        };
+
+        let (best_move, evaluation) = match order.first() {
+            Some(best) => best.clone(),
+            None => {
+                best_order = order;
+                break;
+            }
+        };
+
+        searcher.set_root_move_order(
+            order.iter().map(|(m, _)| m.clone()).collect(),
+        );
+        best_order = order;
+
+        let info = IterationInfo {
+            depth,
+            evaluation,
+            principal_variation: searcher.get_principal_variation(&best_move),
+            stats: stats.clone(),
+            elapsed: start.elapsed(),
+        };
+
+        // There's no point looking any deeper once a forced mate has been
+        // found
+        let is_mate = matches!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(_), _)
+        );
+
+        if let Some(observer) = searcher.observer() {
+            observer.on_iteration(&info);
+        }
+        if on_iteration(&info).is_break() || (is_mate && !limits.infinite) {
+            break;
+        }
+    }
+
+    reset_limits(searcher, last_depth);
+    Ok(best_order)
+}
+
+// Leaves the searcher at `depth` without any other limits, so that limits
+// from this search don't affect later ones
+fn reset_limits<S>(searcher: &mut S, depth: Depth)
+where
+    S: Searcher + ?Sized,
+{
+    searcher.set_limits(&SearchLimits::depth(depth));
+}
+
+/// Wraps a searcher so that every search iteratively deepens until the set
+/// limits are reached, keeping track of every iteration along the way
+pub struct IterativeDeepening<S> {
+    searcher: S,
+    limits: SearchLimits,
+    iterations: Vec<IterationInfo>,
+}
+
+impl<S> IterativeDeepening<S>
+where
+    S: Searcher,
+{
+    pub fn new(searcher: S) -> Self {
+        IterativeDeepening {
+            searcher,
+            limits: SearchLimits::depth(4),
+            iterations: vec![],
+        }
+    }
+
+    /// A view into the iterations completed by the last search
+    pub fn get_iterations(&self) -> &[IterationInfo] {
+        &self.iterations
+    }
+
+    /// Searches `board`, calling `on_iteration` after every completed
+    /// iteration. See `deepen` for details.
+    pub fn run<F>(
+        &mut self,
+        board: &Board,
+        mut on_iteration: F,
+    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str>
+    where
+        F: FnMut(&IterationInfo) -> ControlFlow<()>,
+    {
+        let iterations = &mut self.iterations;
+        iterations.clear();
+
+        deepen(&mut self.searcher, board, &self.limits, |info| {
+            iterations.push(info.clone());
+            on_iteration(info)
+        })
+    }
+}
+
+impl<S> Searcher for IterativeDeepening<S>
+where
+    S: Searcher,
+{
+    fn search_order(
+        &mut self,
+        board: &Board,
+    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
+        self.run(board, |_| ControlFlow::Continue(()))
+    }
+
+    /// Sets when following searches stop. Unlike with other searchers, these
+    /// limit the whole iterative search, see `deepen`, so a search that
+    /// reaches them returns what the last completed iteration found rather
+    /// than giving up.
+    fn set_limits(&mut self, limits: &SearchLimits) {
+        self.limits = limits.clone();
+    }
+
+    fn set_multi_pv(&mut self, lines: usize) {
+        self.searcher.set_multi_pv(lines);
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.searcher.set_observer(observer);
+    }
+
+    fn observer(&mut self) -> Option<&mut dyn SearchObserver> {
+        self.searcher.observer()
+    }
+
+    fn search_stats(&self) -> SearchStats {
+        self.iterations
+            .last()
+            .map_or_else(SearchStats::default, |i| i.stats.clone())
+    }
+
+    fn get_principal_variation(&self, root_move: &ChessMove) -> Vec<ChessMove> {
+        // Iterations that were cut short don't replace the lines found by
+        // earlier ones, so the inner searcher's lines are always complete
+        self.searcher.get_principal_variation(root_move)
+    }
+
+    fn new_game(&mut self) {
+        self.searcher.new_game();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::board::piece::Side;
+    use crate::evaluation::material::MaterialEvaluator;
+    use crate::evaluation::Evaluator;
+    use crate::search::alphabeta::AlphabetaSearch;
+    use crate::search::minimax::MinimaxSearch;
+    use crate::search::observer::PROGRESS_INTERVAL;
+    use crate::search::stop::StopHandle;
+    use crate::search::test_utils::{
+        get_mate_in_two_board, get_mate_searcher, get_material_searcher,
+        get_queen_against_pawn_board,
+    };
+    use crate::search::time_manager::Deadlines;
+
+    use super::*;
+
+    // Evaluates material, and stops the search after a set number of
+    // evaluations, so tests can stop searches at the same point every time
+    struct StoppingEvaluator {
+        stop: StopHandle,
+        remaining: Cell<u32>,
+    }
+
+    impl Evaluator for StoppingEvaluator {
+        fn evaluate(&self, board: &Board) -> Result<Evaluation, &'static str> {
+            match self.remaining.get() {
+                0 => self.stop.stop(),
+                n => self.remaining.set(n - 1),
+            }
+            MaterialEvaluator::new().evaluate(board)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Iteration(Depth),
+        NewBestMove(Evaluation, Depth),
+        CurrentMove(usize, Depth),
+        Progress(u64),
+    }
+
+    // Remembers every event, so tests can look at them after the search
+    struct RecordingObserver(Arc<Mutex<Vec<Event>>>);
+
+    impl SearchObserver for RecordingObserver {
+        fn on_iteration(&mut self, info: &IterationInfo) {
+            self.0.lock().unwrap().push(Event::Iteration(info.depth));
+        }
+
+        fn on_new_best_move(
+            &mut self,
+            _chess_move: &ChessMove,
+            evaluation: Evaluation,
+            depth: Depth,
+        ) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(Event::NewBestMove(evaluation, depth));
+        }
+
+        fn on_current_move(
+            &mut self,
+            _chess_move: &ChessMove,
+            move_number: usize,
+            depth: Depth,
+        ) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(Event::CurrentMove(move_number, depth));
+        }
+
+        fn on_progress(&mut self, stats: &SearchStats) {
+            self.0.lock().unwrap().push(Event::Progress(stats.nodes));
+        }
+    }
+
+    #[test]
+    fn reports_every_iteration() {
+        let board = get_queen_against_pawn_board();
+
+        let mut searcher = IterativeDeepening::new(get_material_searcher());
+        searcher.set_depth(3);
+        searcher.search(&board).unwrap();
+
+        let iterations = searcher.get_iterations();
+        assert_eq!(
+            iterations.iter().map(|i| i.depth).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        for pair in iterations.windows(2) {
+            assert!(pair[0].stats.nodes < pair[1].stats.nodes);
+            assert!(pair[0].elapsed <= pair[1].elapsed);
+        }
+        for iteration in iterations {
+            let pv = &iteration.principal_variation;
+            assert!(!pv.is_empty());
+            assert!(pv.len() <= iteration.depth.into());
+        }
+    }
+
+    #[test]
+    fn stops_between_iterations() {
+        let mut searcher = IterativeDeepening::new(MinimaxSearch::new(
+            MaterialEvaluator::new(),
+        ));
+        searcher.set_depth(4);
+
+        searcher
+            .run(&Board::default(), |info| {
+                if info.depth == 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+
+        assert_eq!(searcher.get_iterations().len(), 2);
+    }
+
+    #[test]
+    fn stops_within_iteration() {
+        let mut searcher = get_material_searcher();
+
+        let now = Instant::now();
+        let deadlines = Deadlines {
+            soft: now + Duration::from_secs(60),
+            hard: now + Duration::from_millis(200),
+        };
+
+        let mut depths = vec![];
+        let limits = SearchLimits {
+            depth: Some(20),
+            deadlines: Some(deadlines),
+            ..Default::default()
+        };
+        let order = deepen(&mut searcher, &Board::default(), &limits, |i| {
+            depths.push(i.depth);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        assert!(!order.is_empty());
+        assert!(*depths.last().unwrap() < 20);
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        let mut searcher = IterativeDeepening::new(get_mate_searcher());
+        searcher.set_depth(6);
+
+        let (_, evaluation) =
+            searcher.search(&get_mate_in_two_board()).unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
+        );
+
+        // The search should have stopped once it found the mate. The mating
+        // move gives check, so it's searched deeper and found an iteration
+        // before the search depth reaches it.
+        assert_eq!(searcher.get_iterations().last().unwrap().depth, 2);
+    }
+
+    #[test]
+    fn respects_node_limit() {
+        let run = || {
+            let mut searcher = IterativeDeepening::new(get_material_searcher());
+            searcher.set_limits(&SearchLimits::nodes(2000));
+            let result = searcher.search(&Board::default()).unwrap();
+
+            let iterations = searcher.get_iterations();
+            assert!(iterations.last().unwrap().stats.nodes <= 2000);
+            (result, iterations.len())
+        };
+
+        // Node limits don't depend on how fast the machine is, so the same
+        // search should always give the same result
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn searches_deep_enough_for_mate() {
+        let mut searcher = IterativeDeepening::new(get_mate_searcher());
+        searcher.set_limits(&SearchLimits::mate(2));
+
+        let (_, evaluation) =
+            searcher.search(&get_mate_in_two_board()).unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
+        );
+
+        // A mate in one move isn't there, so the search has to look further
+        // than that, but not further than the mate could be
+        searcher.set_limits(&SearchLimits::mate(1));
+        let (_, evaluation) =
+            searcher.search(&get_mate_in_two_board()).unwrap();
+        assert!(!matches!(evaluation, Evaluation::Certain(_, _)));
+        assert_eq!(searcher.get_iterations().last().unwrap().depth, 1);
+    }
+
+    #[test]
+    fn keeps_searching_after_mate_when_infinite() {
+        let mut searcher = IterativeDeepening::new(get_mate_searcher());
+        searcher.set_limits(&SearchLimits {
+            infinite: true,
+            nodes: Some(20_000),
+            ..Default::default()
+        });
+
+        searcher.search(&get_mate_in_two_board()).unwrap();
+        assert!(searcher.get_iterations().last().unwrap().depth > 3);
+    }
+
+    #[test]
+    fn returns_best_move_so_far_when_stopped() {
+        let stop = StopHandle::new();
+        let evaluator = StoppingEvaluator {
+            stop: stop.clone(),
+            remaining: Cell::new(5000),
+        };
+        let mut searcher =
+            AlphabetaSearch::new(MaterialEvaluator::new(), evaluator);
+
+        let limits = SearchLimits {
+            depth: Some(20),
+            stop: Some(stop.clone()),
+            ..Default::default()
+        };
+        let mut depths = vec![];
+        let order = deepen(&mut searcher, &Board::default(), &limits, |i| {
+            depths.push(i.depth);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        assert!(stop.is_stopped());
+        assert!(!order.is_empty());
+        assert!(*depths.last().unwrap() < 20);
+    }
+
+    #[test]
+    fn finishes_first_iteration_when_stopped() {
+        let stop = StopHandle::new();
+        stop.stop();
+
+        let mut searcher = IterativeDeepening::new(get_material_searcher());
+        searcher.set_limits(&SearchLimits {
+            stop: Some(stop),
+            ..Default::default()
+        });
+
+        assert!(searcher.search(&Board::default()).is_ok());
+        assert_eq!(searcher.get_iterations().len(), 1);
+    }
+
+    #[test]
+    fn tells_observer_about_progress() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let mut searcher = IterativeDeepening::new(get_material_searcher());
+        searcher.set_observer(Box::new(RecordingObserver(events.clone())));
+        searcher.set_depth(3);
+
+        let (_, evaluation) = searcher.search(&Board::default()).unwrap();
+        let events = events.lock().unwrap();
+
+        let iterations: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Iteration(depth) => Some(*depth),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(iterations, vec![1, 2, 3]);
+
+        // Every root move is searched on every iteration
+        for depth in 1..=3 {
+            let move_numbers: Vec<_> = events
+                .iter()
+                .filter_map(|e| match e {
+                    Event::CurrentMove(n, d) if *d == depth => Some(*n),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(move_numbers, (1..=20).collect::<Vec<_>>());
+        }
+
+        let last_best = events
+            .iter()
+            .rev()
+            .find_map(|e| match e {
+                Event::NewBestMove(evaluation, 3) => Some(*evaluation),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(last_best, evaluation);
+
+        assert!(events.iter().any(|e| matches!(
+            e,
+            Event::Progress(nodes) if *nodes == PROGRESS_INTERVAL
+        )));
+    }
+}
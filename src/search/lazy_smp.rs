@@ -0,0 +1,265 @@
+use std::thread;
+
+use crate::{
+    board::{chess_move::ChessMove, Board},
+    evaluation::{
+        evaluation_result::{Depth, Evaluation},
+        Evaluator,
+    },
+};
+
+use super::{
+    alphabeta::AlphabetaSearch, limits::SearchLimits, observer::SearchObserver,
+    stats::SearchStats, stop::StopHandle, Searcher,
+};
+
+/// Searches with several threads at once, Lazy SMP style
+///
+/// Every thread searches the same position with its own `AlphabetaSearch`,
+/// and they all share one transposition table. The helper threads search a
+/// little differently from the main one, every other helper a ply deeper and
+/// each starting with a different root move, so that they fill the table
+/// with results the main search can use. The main search, on the thread
+/// `search_order` is called from, decides the result, and the helpers are
+/// stopped as soon as it's done.
+///
+/// With a single thread, which is the default, this searches exactly like
+/// the wrapped `AlphabetaSearch`, so results are reproducible.
+pub struct LazySmpSearch<ME, PE> {
+    searcher: AlphabetaSearch<ME, PE>,
+    threads: usize,
+
+    // Kept to hand out to the helpers, which are made anew for every search
+    root_move_order: Vec<ChessMove>,
+
+    // What the helpers did during the last search
+    helper_stats: SearchStats,
+}
+
+impl<ME, PE> LazySmpSearch<ME, PE>
+where
+    ME: Evaluator + Clone + Send,
+    PE: Evaluator + Clone + Send,
+{
+    pub fn new(searcher: AlphabetaSearch<ME, PE>) -> Self {
+        LazySmpSearch {
+            searcher,
+            threads: 1,
+            root_move_order: vec![],
+            helper_stats: SearchStats::default(),
+        }
+    }
+
+    /// Sets how many threads search at once, counting the one searches are
+    /// started from. This is what the UCI `Threads` option controls. At least
+    /// one thread is always used.
+    pub fn set_threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// The searcher doing the main search, for example to change its
+    /// settings. The helpers copy them at the start of every search.
+    pub fn searcher_mut(&mut self) -> &mut AlphabetaSearch<ME, PE> {
+        &mut self.searcher
+    }
+
+    // Makes the searchers for the helper threads. They're stopped with
+    // `stop` rather than the main search's limits, since they should run
+    // for exactly as long as the main search does.
+    fn make_helpers(
+        &self,
+        board: &Board,
+        stop: &StopHandle,
+    ) -> Result<Vec<AlphabetaSearch<ME, PE>>, &'static str> {
+        let mut root_moves = self.root_move_order.clone();
+        for m in board.generate_moves(true)? {
+            if !root_moves.contains(&m) {
+                root_moves.push(m);
+            }
+        }
+
+        let depth = self.searcher.depth();
+        let helpers = (1..self.threads)
+            .map(|index| {
+                let mut helper = self.searcher.helper();
+                helper.set_limits(&SearchLimits {
+                    depth: Some(depth.saturating_add((index % 2) as Depth)),
+                    stop: Some(stop.clone()),
+                    ..Default::default()
+                });
+
+                let mut order = root_moves.clone();
+                let len = order.len();
+                if len > 0 {
+                    order.rotate_left(index % len);
+                }
+                helper.set_root_move_order(order);
+
+                helper
+            })
+            .collect();
+
+        Ok(helpers)
+    }
+}
+
+impl<ME, PE> Searcher for LazySmpSearch<ME, PE>
+where
+    ME: Evaluator + Clone + Send,
+    PE: Evaluator + Clone + Send,
+{
+    fn search_order(
+        &mut self,
+        board: &Board,
+    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
+        self.helper_stats = SearchStats::default();
+        if self.threads == 1 {
+            return self.searcher.search_order(board);
+        }
+
+        let stop = StopHandle::new();
+        let mut helpers = self.make_helpers(board, &stop)?;
+
+        let searcher = &mut self.searcher;
+        let result = thread::scope(|scope| {
+            for helper in &mut helpers {
+                // Only the main search's result counts, so whatever the
+                // helpers find is only used through the transposition table
+                scope.spawn(move || helper.search_order(board).ok());
+            }
+
+            let result = searcher.search_order(board);
+            stop.stop();
+            result
+        });
+
+        for helper in &helpers {
+            self.helper_stats.merge(&helper.search_stats());
+        }
+
+        result
+    }
+
+    fn set_limits(&mut self, limits: &SearchLimits) {
+        self.searcher.set_limits(limits);
+    }
+
+    fn set_root_move_order(&mut self, moves: Vec<ChessMove>) {
+        self.root_move_order = moves.clone();
+        self.searcher.set_root_move_order(moves);
+    }
+
+    fn set_multi_pv(&mut self, lines: usize) {
+        self.searcher.set_multi_pv(lines);
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.searcher.set_observer(observer);
+    }
+
+    fn observer(&mut self) -> Option<&mut dyn SearchObserver> {
+        self.searcher.observer()
+    }
+
+    /// What every thread did during the last search, added up. The time it
+    /// took is the main search's.
+    fn search_stats(&self) -> SearchStats {
+        let mut stats = self.searcher.search_stats();
+        let elapsed = stats.elapsed;
+        stats.merge(&self.helper_stats);
+        stats.elapsed = elapsed;
+        stats
+    }
+
+    fn get_principal_variation(&self, root_move: &ChessMove) -> Vec<ChessMove> {
+        self.searcher.get_principal_variation(root_move)
+    }
+
+    fn new_game(&mut self) {
+        // The helpers share the main searcher's transposition table, so
+        // this clears theirs too
+        self.searcher.new_game();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{game::ChessResult, piece::Side};
+    use crate::search::test_utils::{
+        get_mate_in_two_board, get_mate_searcher, get_material_searcher,
+    };
+
+    use super::*;
+
+    #[test]
+    fn single_thread_searches_like_alphabeta() {
+        let board = Board::default();
+
+        let mut plain = get_material_searcher();
+        plain.set_depth(4);
+        let plain_order = plain.search_order(&board).unwrap();
+
+        let mut searcher = LazySmpSearch::new(get_material_searcher());
+        searcher.set_depth(4);
+        let order = searcher.search_order(&board).unwrap();
+
+        assert_eq!(order, plain_order);
+        assert_eq!(searcher.search_stats().nodes, plain.search_stats().nodes);
+    }
+
+    #[test]
+    fn keeps_the_depth_of_the_searcher() {
+        let mut searcher = get_material_searcher();
+        searcher.set_depth(2);
+
+        let mut searcher = LazySmpSearch::new(searcher);
+        assert_eq!(searcher.searcher_mut().depth(), 2);
+    }
+
+    #[test]
+    fn threads_find_the_same_mate() {
+        let mut searcher = LazySmpSearch::new(get_mate_searcher());
+        searcher.set_threads(4);
+        searcher.set_depth(4);
+
+        let (_, evaluation) =
+            searcher.search(&get_mate_in_two_board()).unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
+        );
+    }
+
+    #[test]
+    fn searches_iteratively_with_threads() {
+        let mut searcher = LazySmpSearch::new(get_material_searcher());
+        searcher.set_threads(3);
+
+        let board = Board::default();
+        let (best_move, _) = searcher
+            .search_with_limits(&board, &SearchLimits::depth(4))
+            .unwrap();
+
+        assert!(board.generate_moves(true).unwrap().contains(&best_move));
+        assert!(searcher.search_stats().nodes > 0);
+    }
+
+    #[test]
+    fn helpers_count_towards_the_stats() {
+        let mut searcher = LazySmpSearch::new(get_material_searcher());
+        searcher.set_threads(2).set_depth(4);
+        searcher.search(&Board::default()).unwrap();
+
+        let stats = searcher.search_stats();
+        assert_eq!(
+            stats.nodes,
+            searcher.searcher_mut().search_stats().nodes
+                + searcher.helper_stats.nodes
+        );
+        assert_eq!(
+            stats.elapsed,
+            searcher.searcher_mut().search_stats().elapsed
+        );
+    }
+}
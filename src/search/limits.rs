@@ -0,0 +1,191 @@
+use std::time::{Duration, Instant};
+
+use crate::evaluation::evaluation_result::Depth;
+
+use super::{stop::StopHandle, time_manager::Deadlines};
+
+/// Describes when a search should stop
+///
+/// Limits can be combined, in which case the search stops as soon as any of
+/// them is reached. Without any limits, a search goes on until it reaches the
+/// deepest depth it supports or finds a forced mate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// How many plies ahead to look at most
+    pub depth: Option<Depth>,
+
+    /// How many nodes to look at most, over all iterations. Unlike time
+    /// limits, this makes searches give the same result on every machine.
+    pub nodes: Option<u64>,
+
+    /// How long to search for, counting from the start of the search
+    pub move_time: Option<Duration>,
+
+    /// Deadlines from a time manager, see `Deadlines`
+    pub deadlines: Option<Deadlines>,
+
+    /// Only look for a forced mate in this many moves. The search stops once
+    /// a mate is found, or once it has looked deep enough to find any mate
+    /// this short.
+    pub mate: Option<u8>,
+
+    /// Ignore the depth and mate limits, and don't stop after finding a
+    /// mate, so the search only stops when it's told to or runs out of time
+    /// or nodes
+    pub infinite: bool,
+
+    /// Stops the search once it's stopped, keeping the best move found so
+    /// far
+    pub stop: Option<StopHandle>,
+}
+
+impl SearchLimits {
+    /// Limits that only stop the search at `depth` plies
+    pub fn depth(depth: Depth) -> Self {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    /// Limits that only stop the search after `nodes` nodes
+    pub fn nodes(nodes: u64) -> Self {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    /// Limits that only stop the search after `move_time`
+    pub fn move_time(move_time: Duration) -> Self {
+        SearchLimits {
+            move_time: Some(move_time),
+            ..Default::default()
+        }
+    }
+
+    /// Limits that only stop the search at the given deadlines
+    pub fn deadlines(deadlines: Deadlines) -> Self {
+        SearchLimits {
+            deadlines: Some(deadlines),
+            ..Default::default()
+        }
+    }
+
+    /// Limits for finding a forced mate in `moves` moves
+    pub fn mate(moves: u8) -> Self {
+        SearchLimits {
+            mate: Some(moves),
+            ..Default::default()
+        }
+    }
+
+    /// Limits that never stop the search on their own
+    pub fn infinite() -> Self {
+        SearchLimits {
+            infinite: true,
+            ..Default::default()
+        }
+    }
+
+    /// The deepest a search with these limits should look. This is always at
+    /// least 1, so that there's a move to play.
+    pub fn max_depth(&self) -> Depth {
+        if self.infinite {
+            return Depth::MAX;
+        }
+
+        // A mate in N moves takes 2N - 1 plies
+        let mate_depth =
+            self.mate.map(|n| n.saturating_mul(2).saturating_sub(1));
+
+        let depth = match (self.depth, mate_depth) {
+            (Some(depth), Some(mate_depth)) => depth.min(mate_depth),
+            (depth, mate_depth) => depth.or(mate_depth).unwrap_or(Depth::MAX),
+        };
+        depth.max(1)
+    }
+
+    /// The deadlines a search with these limits that started at `start` has
+    /// to respect, combining `move_time` and `deadlines`
+    pub fn get_deadlines(&self, start: Instant) -> Option<Deadlines> {
+        let move_time = self.move_time.map(|t| Deadlines {
+            soft: start + t,
+            hard: start + t,
+        });
+
+        match (move_time, self.deadlines) {
+            (Some(a), Some(b)) => Some(Deadlines {
+                soft: a.soft.min(b.soft),
+                hard: a.hard.min(b.hard),
+            }),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::search::test_utils::get_material_searcher;
+    use crate::search::Searcher;
+
+    use super::*;
+
+    #[test]
+    fn picks_shallowest_depth() {
+        assert_eq!(SearchLimits::default().max_depth(), Depth::MAX);
+        assert_eq!(SearchLimits::depth(5).max_depth(), 5);
+        assert_eq!(SearchLimits::mate(2).max_depth(), 3);
+
+        let limits = SearchLimits {
+            depth: Some(2),
+            mate: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(limits.max_depth(), 2);
+
+        let limits = SearchLimits {
+            depth: Some(2),
+            infinite: true,
+            ..Default::default()
+        };
+        assert_eq!(limits.max_depth(), Depth::MAX);
+    }
+
+    #[test]
+    fn always_searches_a_ply() {
+        assert_eq!(SearchLimits::depth(0).max_depth(), 1);
+        assert_eq!(SearchLimits::mate(0).max_depth(), 1);
+
+        let mut searcher = get_material_searcher();
+        for limits in [SearchLimits::depth(0), SearchLimits::mate(0)] {
+            assert!(searcher
+                .search_with_limits(&Board::default(), &limits)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn combines_move_time_and_deadlines() {
+        let start = Instant::now();
+        assert_eq!(SearchLimits::default().get_deadlines(start), None);
+
+        let limits = SearchLimits {
+            move_time: Some(Duration::from_secs(2)),
+            deadlines: Some(Deadlines {
+                soft: start + Duration::from_secs(1),
+                hard: start + Duration::from_secs(3),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            limits.get_deadlines(start),
+            Some(Deadlines {
+                soft: start + Duration::from_secs(1),
+                hard: start + Duration::from_secs(2),
+            })
+        );
+    }
+}
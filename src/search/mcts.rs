@@ -0,0 +1,809 @@
+use std::{cmp::Reverse, convert::TryFrom, f64::consts::SQRT_2, time::Instant};
+
+use crate::{
+    board::{chess_move::ChessMove, piece::Side, Board},
+    evaluation::{
+        evaluation_result::{Depth, Evaluation},
+        Evaluator,
+    },
+};
+
+use super::{
+    check_deadline, check_node_limit, check_stop,
+    iterative_deepening::IterationInfo,
+    limits::SearchLimits,
+    observer::{NoopObserver, SearchObserver, PROGRESS_INTERVAL},
+    rng::Rng,
+    stats::SearchStats,
+    Searcher,
+};
+
+/// How many playouts a search does, unless set otherwise
+const DEFAULT_PLAYOUTS: u64 = 1000;
+
+/// How many plies a playout plays at most, unless set otherwise
+const DEFAULT_PLAYOUT_LENGTH: Depth = 8;
+
+/// Seed for picking random moves, unless set otherwise
+const DEFAULT_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// How `MctsSearch` picks which move to follow down the tree
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    /// UCT, which adds `exploration * sqrt(ln(parent visits) / visits)` to
+    /// the average result of a move. Moves that haven't been visited yet are
+    /// always visited first. This is the default, with an exploration of
+    /// the square root of 2.
+    Uct { exploration: f64 },
+
+    /// PUCT, as used by AlphaZero, which adds
+    /// `exploration * prior * sqrt(parent visits) / (1 + visits)` to the
+    /// average result of a move. The prior is the evaluator's win
+    /// probability after the move, as a share of the other moves', so moves
+    /// that look good are tried first. Moves that haven't been visited yet
+    /// count as even.
+    Puct { exploration: f64 },
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Selection::Uct {
+            exploration: SQRT_2,
+        }
+    }
+}
+
+/// How `MctsSearch` picks moves during playouts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlayoutPolicy {
+    /// Plays random moves. This is the default.
+    #[default]
+    Random,
+
+    /// Plays the move whose position the evaluator thinks is best for the
+    /// side to move, and a random one out of those if there are several
+    Evaluator,
+}
+
+/// Searches with Monte Carlo Tree Search
+///
+/// Instead of looking at every move to a fixed depth, MCTS grows a tree of
+/// positions one at a time, towards the moves that have done best so far.
+/// Every playout follows the tree down by the selection rule, adds a position
+/// to it, plays on from there by the playout policy, and counts the result
+/// for every move on the way. Results are win probabilities, which the
+/// evaluator's estimates are converted to with
+/// `Evaluation::win_probability`.
+///
+/// Root moves are ranked by how often they were visited, with evaluations
+/// from their average results, see `root_visits`. Only moves that end the
+/// game right away get a certain evaluation.
+///
+/// Unlike the other searchers, searches stop at their limits with the result
+/// so far rather than `SEARCH_ABORTED`, as long as every root move has been
+/// visited once. `search_with_limits` runs a single search until the limits
+/// are reached, rather than searching iteratively deeper.
+pub struct MctsSearch<E> {
+    depth: Depth,
+    limits: SearchLimits,
+
+    // The soft deadline of the current search, worked out from `limits` when
+    // it starts
+    deadline: Option<Instant>,
+    observer: Box<dyn SearchObserver>,
+    stats: SearchStats,
+
+    // When the current search started, for reporting progress
+    search_start: Instant,
+
+    playouts: u64,
+    playout_length: Depth,
+    selection: Selection,
+    playout_policy: PlayoutPolicy,
+    seed: u64,
+    rng: Rng,
+
+    // Every position in the tree, starting with the root
+    tree: Vec<Node>,
+
+    // How often every root move was visited by the last search, in the same
+    // order as `search_order` returned them
+    root_visits: Vec<(ChessMove, u64)>,
+
+    evaluator: E,
+}
+
+// A position in the tree
+struct Node {
+    // The move leading to this position, and the side that made it
+    chess_move: ChessMove,
+    side: Side,
+
+    // The positions after every move, or `None` until this node is expanded
+    children: Option<Vec<usize>>,
+
+    // Set when the game is over in this position
+    result: Option<Evaluation>,
+
+    visits: u64,
+
+    // The results of every playout through here added up, for `side`
+    wins: f64,
+
+    // See `Selection::Puct`
+    prior: f64,
+}
+
+impl Node {
+    fn new(chess_move: ChessMove, side: Side) -> Self {
+        Node {
+            chess_move,
+            side,
+            children: None,
+            result: None,
+            visits: 0,
+            wins: 0.0,
+            prior: 0.0,
+        }
+    }
+
+    // The average result for `side`, which counts as even until there is one
+    fn mean(&self) -> f64 {
+        if self.visits == 0 {
+            0.5
+        } else {
+            self.wins / self.visits as f64
+        }
+    }
+
+    // The evaluation of this position as far as the playouts can tell
+    fn estimate(&self) -> Evaluation {
+        let white_wins = match self.side {
+            Side::White => self.mean(),
+            Side::Black => 1.0 - self.mean(),
+        };
+        Evaluation::from_win_probability(white_wins)
+    }
+}
+
+impl<E> MctsSearch<E>
+where
+    E: Evaluator,
+{
+    pub fn new(evaluator: E) -> Self {
+        MctsSearch {
+            depth: Depth::MAX,
+            limits: SearchLimits::default(),
+            deadline: None,
+            observer: Box::new(NoopObserver),
+            stats: SearchStats::default(),
+            search_start: Instant::now(),
+            playouts: DEFAULT_PLAYOUTS,
+            playout_length: DEFAULT_PLAYOUT_LENGTH,
+            selection: Selection::default(),
+            playout_policy: PlayoutPolicy::default(),
+            seed: DEFAULT_SEED,
+            rng: Rng::new(DEFAULT_SEED),
+            tree: vec![],
+            root_visits: vec![],
+            evaluator,
+        }
+    }
+
+    /// Sets how many playouts a search does, unless it reaches one of its
+    /// limits first
+    pub fn set_playouts(&mut self, playouts: u64) -> &mut Self {
+        self.playouts = playouts;
+        self
+    }
+
+    /// Sets how many plies playouts play before the evaluator judges the
+    /// position they end in. With 0, positions are judged as soon as they're
+    /// added to the tree.
+    pub fn set_playout_length(&mut self, plies: Depth) -> &mut Self {
+        self.playout_length = plies;
+        self
+    }
+
+    /// Sets how the search picks which move to follow down the tree, see
+    /// `Selection`
+    pub fn set_selection(&mut self, selection: Selection) -> &mut Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Sets how moves are picked during playouts, see `PlayoutPolicy`
+    pub fn set_playout_policy(&mut self, policy: PlayoutPolicy) -> &mut Self {
+        self.playout_policy = policy;
+        self
+    }
+
+    /// Sets the seed random moves are picked with. Every search starts from
+    /// it, so searching the same position twice gives the same result.
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    /// How often every root move was visited by the last search, most
+    /// visited first, the same order `search_order` returned them in
+    pub fn root_visits(&self) -> &[(ChessMove, u64)] {
+        &self.root_visits
+    }
+
+    // Whether the search has reached its limits, after every root move has
+    // been visited at least once
+    fn limits_reached(&self, playouts: u64) -> bool {
+        let root_visited = self.tree[0]
+            .children
+            .iter()
+            .flatten()
+            .all(|&c| self.tree[c].visits > 0);
+
+        root_visited
+            && (playouts >= self.max_playouts()
+                || check_stop(self.limits.stop.as_ref()).is_err()
+                || check_deadline(self.deadline).is_err()
+                || check_node_limit(self.stats.nodes, self.limits.nodes)
+                    .is_err())
+    }
+
+    // How many playouts a search does at most. Searches with limits that
+    // stop them on their own go on until they're reached.
+    fn max_playouts(&self) -> u64 {
+        let limits = &self.limits;
+        let stops_on_its_own = limits.nodes.is_some()
+            || limits.move_time.is_some()
+            || limits.deadlines.is_some()
+            || limits.infinite;
+
+        if stops_on_its_own {
+            u64::MAX
+        } else {
+            self.playouts
+        }
+    }
+
+    // Counts a position looked at, `ply` plies from the root, and tells the
+    // observer how the search is going every `PROGRESS_INTERVAL` positions
+    fn count_node(&mut self, ply: usize) {
+        self.stats.count_node(ply);
+
+        if self.stats.nodes.is_multiple_of(PROGRESS_INTERVAL) {
+            self.stats.elapsed = self.search_start.elapsed();
+            self.observer.on_progress(&self.stats);
+        }
+    }
+
+    // Follows the tree down from the root, adds a position to it, plays out
+    // the game from there and counts the result for every position on the way
+    fn run_playout(&mut self, root: &Board) -> Result<(), &'static str> {
+        let mut board = root.clone();
+        let mut path = vec![0];
+        self.count_node(0);
+
+        let mut node = 0;
+        while let Some(child) = self.select_child(node) {
+            board.make_move(self.tree[child].chess_move.clone(), false)?;
+            node = child;
+            path.push(node);
+            self.count_node(path.len() - 1);
+        }
+
+        // Positions are only added to the tree once they've been played out
+        // from before, so that the tree grows towards the better moves
+        let can_grow = path.len() - 1 < usize::from(self.depth);
+        if can_grow && (node == 0 || self.tree[node].visits > 0) {
+            self.expand(node, &board)?;
+
+            if let Some(child) = self.select_child(node) {
+                board.make_move(self.tree[child].chess_move.clone(), false)?;
+                node = child;
+                path.push(node);
+                self.count_node(path.len() - 1);
+            }
+        }
+
+        let white_wins = match self.tree[node].result {
+            Some(result) => result.win_probability(),
+            None => self.playout(board, path.len())?,
+        };
+
+        for index in path {
+            let node = &mut self.tree[index];
+            node.visits += 1;
+            node.wins += match node.side {
+                Side::White => white_wins,
+                Side::Black => 1.0 - white_wins,
+            };
+        }
+
+        Ok(())
+    }
+
+    // Adds the positions after every move in `board` to the tree, as
+    // children of `node`
+    fn expand(
+        &mut self,
+        node: usize,
+        board: &Board,
+    ) -> Result<(), &'static str> {
+        let side = board.get_current_side();
+        let moves = board.generate_moves(true)?;
+
+        if moves.is_empty() {
+            self.tree[node].result = Some(self.final_evaluation(board)?);
+            self.tree[node].children = Some(vec![]);
+            return Ok(());
+        }
+
+        let mut children = vec![];
+        for m in moves {
+            let mut child = Node::new(m, side);
+
+            // Priors take an evaluation of every move, so they're only worked
+            // out when they're used
+            if let Selection::Puct { .. } = self.selection {
+                let mut moved_board = board.clone();
+                moved_board.make_move(child.chess_move.clone(), false)?;
+                child.prior = self
+                    .evaluator
+                    .evaluate(&moved_board)?
+                    .relative_to(side)
+                    .win_probability();
+            }
+
+            children.push(self.tree.len());
+            self.tree.push(child);
+        }
+
+        let total: f64 = children.iter().map(|&c| self.tree[c].prior).sum();
+        for &c in &children {
+            self.tree[c].prior = if total > 0.0 {
+                self.tree[c].prior / total
+            } else {
+                1.0 / children.len() as f64
+            };
+        }
+
+        self.tree[node].children = Some(children);
+        Ok(())
+    }
+
+    // The child of `node` to follow down the tree, or `None` if it hasn't
+    // been expanded or the game is over there
+    fn select_child(&self, node: usize) -> Option<usize> {
+        let children = self.tree[node].children.as_ref()?;
+
+        // Every root move gets visited once first, so they all have an
+        // evaluation even if the search stops early
+        if node == 0 {
+            if let Some(&child) =
+                children.iter().find(|&&c| self.tree[c].visits == 0)
+            {
+                return Some(child);
+            }
+        }
+
+        let parent_visits = self.tree[node].visits as f64;
+        let score = |child: &Node| match self.selection {
+            Selection::Uct { exploration } => {
+                if child.visits == 0 {
+                    f64::INFINITY
+                } else {
+                    let visits = child.visits as f64;
+                    child.mean()
+                        + exploration * (parent_visits.ln() / visits).sqrt()
+                }
+            }
+            Selection::Puct { exploration } => {
+                let visits = child.visits as f64;
+                child.mean()
+                    + exploration * child.prior * parent_visits.sqrt()
+                        / (1.0 + visits)
+            }
+        };
+
+        // Out of equally good moves, the first one is picked
+        let mut best: Option<(usize, f64)> = None;
+        for &child in children {
+            let child_score = score(&self.tree[child]);
+            if best.is_none_or(|(_, s)| child_score > s) {
+                best = Some((child, child_score));
+            }
+        }
+
+        best.map(|(child, _)| child)
+    }
+
+    // Plays moves from `board`, which is `ply` plies from the root, until the
+    // game ends or the playout length is reached. Returns the chance White
+    // wins from the position the playout ends in.
+    fn playout(
+        &mut self,
+        mut board: Board,
+        ply: usize,
+    ) -> Result<f64, &'static str> {
+        for played in 0.. {
+            let moves = board.generate_moves(true)?;
+            if moves.is_empty() {
+                return Ok(self.final_evaluation(&board)?.win_probability());
+            }
+            if played >= self.playout_length {
+                break;
+            }
+
+            let chess_move = match self.playout_policy {
+                PlayoutPolicy::Random => {
+                    moves[self.rng.below(moves.len())].clone()
+                }
+                PlayoutPolicy::Evaluator => {
+                    self.best_looking_move(&board, moves)?
+                }
+            };
+            board.make_move(chess_move, false)?;
+            self.count_node(ply + usize::from(played));
+        }
+
+        Ok(self.evaluator.evaluate(&board)?.win_probability())
+    }
+
+    // The move leading to the position the evaluator likes best for the side
+    // to move in `board`, picked at random out of equally good ones
+    fn best_looking_move(
+        &mut self,
+        board: &Board,
+        moves: Vec<ChessMove>,
+    ) -> Result<ChessMove, &'static str> {
+        let side = board.get_current_side();
+        let mut best_moves = vec![];
+        let mut best_evaluation = None;
+
+        for m in moves {
+            let mut moved_board = board.clone();
+            moved_board.make_move(m.clone(), false)?;
+            let evaluation =
+                Some(self.evaluator.evaluate(&moved_board)?.relative_to(side));
+
+            if evaluation > best_evaluation {
+                best_evaluation = evaluation;
+                best_moves.clear();
+            }
+            if evaluation == best_evaluation {
+                best_moves.push(m);
+            }
+        }
+
+        let index = self.rng.below(best_moves.len());
+        Ok(best_moves.swap_remove(index))
+    }
+
+    // The evaluation of a position where the game is over. Checkmate and
+    // stalemate are recognised whatever the evaluator is.
+    fn final_evaluation(
+        &self,
+        board: &Board,
+    ) -> Result<Evaluation, &'static str> {
+        match board.get_game_result()? {
+            Some(result) => Ok(Evaluation::Certain(result, 0)),
+            None => self.evaluator.evaluate(board),
+        }
+    }
+
+    // The line of play the tree expects after the root move `child`, always
+    // following the most visited move
+    fn principal_variation(&self, child: usize) -> Vec<ChessMove> {
+        let mut line = vec![self.tree[child].chess_move.clone()];
+        let mut node = child;
+
+        while let Some(children) = &self.tree[node].children {
+            match children
+                .iter()
+                .filter(|&&c| self.tree[c].visits > 0)
+                .max_by_key(|&&c| (self.tree[c].visits, Reverse(c)))
+            {
+                Some(&next) => {
+                    line.push(self.tree[next].chess_move.clone());
+                    node = next;
+                }
+                None => break,
+            }
+        }
+
+        line
+    }
+}
+
+impl<E> Searcher for MctsSearch<E>
+where
+    E: Evaluator,
+{
+    fn search_order(
+        &mut self,
+        board: &Board,
+    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
+        self.stats = SearchStats::default();
+        self.search_start = Instant::now();
+        self.deadline =
+            self.limits.get_deadlines(self.search_start).map(|d| d.soft);
+        self.rng = Rng::new(self.seed);
+        self.root_visits.clear();
+
+        let side = board.get_current_side();
+        self.tree = vec![Node::new(ChessMove::NullMove, side.flip())];
+
+        if board.generate_moves(true)?.is_empty() {
+            self.stats.elapsed = self.search_start.elapsed();
+            return Ok(vec![(
+                ChessMove::NullMove,
+                self.final_evaluation(board)?,
+            )]);
+        }
+
+        let mut playouts = 0;
+        loop {
+            if self.tree[0].children.is_some() && self.limits_reached(playouts)
+            {
+                break;
+            }
+
+            self.run_playout(board)?;
+            playouts += 1;
+        }
+        self.stats.elapsed = self.search_start.elapsed();
+
+        // The sort is stable, so moves that were visited equally often stay
+        // in the order they were generated in
+        let mut root_moves = self.tree[0].children.clone().unwrap_or_default();
+        root_moves.sort_by_key(|&c| Reverse(self.tree[c].visits));
+
+        self.root_visits = root_moves
+            .iter()
+            .map(|&c| (self.tree[c].chess_move.clone(), self.tree[c].visits))
+            .collect();
+
+        // Results of the game are a move away from the root
+        Ok(root_moves
+            .iter()
+            .map(|&c| {
+                let node = &self.tree[c];
+                let evaluation = match node.result {
+                    Some(result) => result.deepen(),
+                    None => node.estimate(),
+                };
+                (node.chess_move.clone(), evaluation)
+            })
+            .collect())
+    }
+
+    /// The depth is how deep the tree may grow, counting from the root.
+    /// Positions deeper than that are only played out. Searches without time
+    /// or node limits do as many playouts as set with `set_playouts`, and
+    /// mate limits aren't supported.
+    ///
+    /// Since searches can stop at any time, they stop at the soft deadline,
+    /// with the result so far.
+    fn set_limits(&mut self, limits: &SearchLimits) {
+        self.depth = limits.max_depth().max(1);
+        self.limits = limits.clone();
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.observer = observer;
+    }
+
+    fn observer(&mut self) -> Option<&mut dyn SearchObserver> {
+        Some(self.observer.as_mut())
+    }
+
+    fn search_stats(&self) -> SearchStats {
+        self.stats.clone()
+    }
+
+    fn get_principal_variation(&self, root_move: &ChessMove) -> Vec<ChessMove> {
+        self.tree
+            .first()
+            .and_then(|root| root.children.as_ref())
+            .and_then(|children| {
+                children
+                    .iter()
+                    .find(|&&c| self.tree[c].chess_move == *root_move)
+            })
+            .map(|&c| self.principal_variation(c))
+            .unwrap_or_else(|| vec![root_move.clone()])
+    }
+
+    /// Does a single search that stops once one of `limits` is reached, as
+    /// described for `set_limits`, rather than searching iteratively deeper,
+    /// and tells the observer about it as one iteration
+    fn search_with_limits(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        let start = Instant::now();
+        let (previous_limits, depth) = (self.limits.clone(), self.depth);
+
+        self.set_limits(limits);
+        let result = self.search_order(board);
+        self.limits = previous_limits;
+        self.depth = depth;
+
+        let (best_move, evaluation) =
+            result?.first().cloned().ok_or("No moves possible")?;
+        let principal_variation = self.get_principal_variation(&best_move);
+        let info = IterationInfo {
+            depth: Depth::try_from(principal_variation.len())
+                .unwrap_or(Depth::MAX),
+            evaluation,
+            principal_variation,
+            stats: self.stats.clone(),
+            elapsed: start.elapsed(),
+        };
+        self.observer.on_iteration(&info);
+
+        Ok((best_move, evaluation))
+    }
+
+    fn new_game(&mut self) {
+        self.tree.clear();
+        self.root_visits.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::board::{game::ChessResult, square::Square};
+    use crate::evaluation::material::MaterialEvaluator;
+    use crate::search::stop::StopHandle;
+
+    use super::*;
+
+    fn get_mate_in_one_board() -> Board {
+        Board::from_art(
+            ".k......\n\
+             ........\n\
+             .K......\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             .......R\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let mut searcher = MctsSearch::new(MaterialEvaluator::new());
+        searcher.set_playouts(500);
+
+        let (best_move, evaluation) =
+            searcher.search(&get_mate_in_one_board()).unwrap();
+        assert_eq!(
+            best_move,
+            ChessMove::SimpleMove(
+                Square { file: 7, rank: 0 },
+                Square { file: 7, rank: 7 }
+            )
+        );
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 1)
+        );
+    }
+
+    #[test]
+    fn ranks_root_moves_by_visits() {
+        let mut searcher = MctsSearch::new(MaterialEvaluator::new());
+        searcher.set_playouts(200).set_playout_length(4);
+
+        let board = Board::default();
+        let order = searcher.search_order(&board).unwrap();
+        let visits = searcher.root_visits();
+
+        assert_eq!(order.len(), 20);
+        assert_eq!(visits.len(), 20);
+        for ((m, _), (visited_move, _)) in order.iter().zip(visits) {
+            assert_eq!(m, visited_move);
+        }
+        assert!(visits.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!(visits.iter().all(|(_, v)| *v > 0));
+        assert_eq!(visits.iter().map(|(_, v)| v).sum::<u64>(), 200);
+    }
+
+    #[test]
+    fn puct_with_evaluator_playouts_takes_free_queen() {
+        let board = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ........\n\
+             ...q....\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ...RK...\n",
+        )
+        .unwrap();
+
+        let mut searcher = MctsSearch::new(MaterialEvaluator::new());
+        searcher
+            .set_playouts(300)
+            .set_playout_length(2)
+            .set_selection(Selection::Puct { exploration: 1.5 })
+            .set_playout_policy(PlayoutPolicy::Evaluator);
+
+        let (best_move, evaluation) = searcher.search(&board).unwrap();
+        assert_eq!(
+            best_move,
+            ChessMove::SimpleMove(
+                Square { file: 3, rank: 0 },
+                Square { file: 3, rank: 4 }
+            )
+        );
+        assert!(evaluation > Evaluation::Estimate(0));
+    }
+
+    #[test]
+    fn same_seed_gives_same_result() {
+        let board = Board::default();
+        let mut searcher = MctsSearch::new(MaterialEvaluator::new());
+        searcher.set_playouts(100);
+
+        let first = searcher.search_order(&board).unwrap();
+        let first_visits = searcher.root_visits().to_vec();
+        let second = searcher.search_order(&board).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first_visits, searcher.root_visits());
+    }
+
+    #[test]
+    fn stops_at_node_limit() {
+        let mut searcher = MctsSearch::new(MaterialEvaluator::new());
+        searcher.set_playouts(10);
+
+        searcher
+            .search_with_limits(&Board::default(), &SearchLimits::nodes(2000))
+            .unwrap();
+
+        // Limits are checked between playouts, which are only a few nodes
+        let nodes = searcher.search_stats().nodes;
+        assert!(nodes >= 2000);
+        assert!(nodes < 2100);
+    }
+
+    #[test]
+    fn stops_at_move_time() {
+        let mut searcher = MctsSearch::new(MaterialEvaluator::new());
+
+        let start = Instant::now();
+        searcher
+            .search_with_limits(
+                &Board::default(),
+                &SearchLimits::move_time(Duration::from_millis(50)),
+            )
+            .unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(searcher.search_stats().nodes > 0);
+    }
+
+    #[test]
+    fn visits_every_root_move_even_when_stopped() {
+        let mut searcher = MctsSearch::new(MaterialEvaluator::new());
+
+        let stop = StopHandle::new();
+        stop.stop();
+        searcher.set_limits(&SearchLimits {
+            stop: Some(stop),
+            ..Default::default()
+        });
+
+        let order = searcher.search_order(&Board::default()).unwrap();
+        assert_eq!(order.len(), 20);
+        assert!(searcher.root_visits().iter().all(|(_, v)| *v == 1));
+    }
+}
@@ -1,75 +1,186 @@
-use crate::evaluation::evaluation_result::Evaluation;
+use std::{convert::TryFrom, time::Instant};
+
+use crate::evaluation::evaluation_result::{Depth, Evaluation};
 use crate::{
     board::{chess_move::ChessMove, piece::Side, Board},
     evaluation::Evaluator,
 };
 
-use super::Searcher;
+use super::{
+    check_deadline, check_node_limit, check_stop,
+    limits::SearchLimits,
+    observer::{NoopObserver, SearchObserver, PROGRESS_INTERVAL},
+    stats::SearchStats,
+    Searcher, SEARCH_ABORTED,
+};
+
+/// A move, its evaluation, and the line of play expected after it, starting
+/// with the move itself
+type SearchedMove = (ChessMove, Evaluation, Vec<ChessMove>);
 
-#[derive(Clone)]
 pub struct MinimaxSearch<E> {
     depth: usize,
+    limits: SearchLimits,
+
+    // The hard deadline of the current search, worked out from `limits` when
+    // it starts
+    deadline: Option<Instant>,
+    observer: Box<dyn SearchObserver>,
+    stats: SearchStats,
+
+    // When the current search started, for reporting progress
+    search_start: Instant,
+
+    // The line expected after every root move, as found by the last
+    // completed search
+    principal_variations: Vec<Vec<ChessMove>>,
+
+    // The best root move the current search has found so far, which `search`
+    // falls back on when the search is stopped before it's done
+    best_root_move: Option<(ChessMove, Evaluation)>,
+
     evaluator: E,
 }
 
+/// Observers can't be cloned, so a clone starts out without one
+impl<E> Clone for MinimaxSearch<E>
+where
+    E: Clone,
+{
+    fn clone(&self) -> Self {
+        MinimaxSearch {
+            depth: self.depth,
+            limits: self.limits.clone(),
+            deadline: self.deadline,
+            observer: Box::new(NoopObserver),
+            stats: self.stats.clone(),
+            search_start: self.search_start,
+            principal_variations: self.principal_variations.clone(),
+            best_root_move: self.best_root_move.clone(),
+            evaluator: self.evaluator.clone(),
+        }
+    }
+}
+
 impl<E> MinimaxSearch<E>
 where
     E: Evaluator,
 {
-    #[allow(dead_code)]
     pub fn new(evaluator: E) -> Self {
-        MinimaxSearch {
-            depth: 4,
+        let mut searcher = MinimaxSearch {
+            // Both set through `set_limits` below, so they can't disagree
+            depth: 0,
+            limits: SearchLimits::default(),
+            deadline: None,
+            observer: Box::new(NoopObserver),
+            stats: SearchStats::default(),
+            search_start: Instant::now(),
+            principal_variations: vec![],
+            best_root_move: None,
             evaluator,
+        };
+        searcher.set_limits(&SearchLimits::depth(5));
+
+        searcher
+    }
+
+    // Returns `SEARCH_ABORTED` once the search is stopped, or the deadline or
+    // the node limit is reached
+    fn check_limits(&self) -> Result<(), &'static str> {
+        check_stop(self.limits.stop.as_ref())?;
+        check_deadline(self.deadline)?;
+        check_node_limit(self.stats.nodes, self.limits.nodes)
+    }
+
+    // Tells the observer how the search is going, every `PROGRESS_INTERVAL`
+    // nodes
+    fn report_progress(&mut self) {
+        if self.stats.nodes.is_multiple_of(PROGRESS_INTERVAL) {
+            self.stats.elapsed = self.search_start.elapsed();
+            self.observer.on_progress(&self.stats);
         }
     }
 
     fn search_order_impl(
-        &self,
+        &mut self,
         board: &Board,
         depth: usize,
-    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
+    ) -> Result<Vec<SearchedMove>, &'static str> {
+        self.check_limits()?;
+        self.stats.count_node(self.depth - depth);
+        self.report_progress();
+
         let moves = board.generate_moves(true)?;
 
-        let mut evaluations = moves
-            .iter()
-            .map(|m| {
-                let mut moved_board = board.clone();
-
-                // We know this move shouldn't fail, since this was
-                // generated by the movegen engine
-                moved_board.make_move(m.clone(), false).unwrap();
-
-                (m, moved_board)
-            })
-            .filter_map(|(m, moved_board)| {
-                let mut evaluation = None;
-                // If depth isn't 0, try to search with depth - 1.
-                if depth != 0 {
-                    evaluation = self
-                        .search_order_impl(&moved_board, depth - 1)
-                        .ok()?
-                        .into_iter()
-                        .next()
-                        .map(|(_, eval)| eval.deepen());
-                }
+        let is_root = depth == self.depth;
+        // Our depth counts the plies searched after the first one
+        let reported_depth =
+            Depth::try_from(self.depth + 1).unwrap_or(Depth::MAX);
+
+        let mut evaluations: Vec<SearchedMove> = vec![];
+        for (move_number, m) in (1..).zip(moves) {
+            if is_root {
+                self.observer
+                    .on_current_move(&m, move_number, reported_depth);
+            }
+
+            let mut moved_board = board.clone();
+
+            // We know this move shouldn't fail, since this was
+            // generated by the movegen engine
+            moved_board.make_move(m.clone(), false).unwrap();
 
-                // If we didn't find any evaluation, either because the
-                // depth was zero or because the search failed to find any
-                // possible moves, just return the current board evaluated
-                if evaluation.is_none() {
-                    evaluation = self
-                        .evaluator
-                        .evaluate(&moved_board)
-                        .ok()
-                        .map(|e| e.deepen());
+            let mut evaluation = None;
+            let mut line = vec![m.clone()];
+            // If depth isn't 0, try to search with depth - 1.
+            if depth != 0 {
+                evaluation = self
+                    .search_order_impl(&moved_board, depth - 1)?
+                    .into_iter()
+                    .next()
+                    .map(|(_, eval, continuation)| {
+                        line.extend(continuation);
+                        eval.deepen()
+                    });
+            }
+
+            // If we didn't find any evaluation, either because the
+            // depth was zero or because the search failed to find any
+            // possible moves, just return the current board evaluated
+            if evaluation.is_none() {
+                self.stats.count_node(self.depth - depth + 1);
+                self.report_progress();
+                evaluation = self
+                    .evaluator
+                    .evaluate(&moved_board)
+                    .ok()
+                    .map(|e| e.deepen());
+            }
+
+            if let Some(some_eval) = evaluation {
+                if is_root {
+                    let is_new_best = evaluations.iter().all(|(_, e, _)| {
+                        match board.get_current_side() {
+                            Side::White => some_eval > *e,
+                            Side::Black => some_eval < *e,
+                        }
+                    });
+                    if is_new_best {
+                        self.best_root_move = Some((m.clone(), some_eval));
+                        self.observer.on_new_best_move(
+                            &m,
+                            some_eval,
+                            reported_depth,
+                        );
+                    }
                 }
 
-                evaluation.map(|some_eval| (m.clone(), some_eval))
-            })
-            .collect::<Vec<_>>();
+                evaluations.push((m, some_eval, line));
+            }
+        }
 
-        evaluations.sort_unstable_by(|(_, eval1), (_, eval2)| eval2.cmp(eval1));
+        evaluations
+            .sort_unstable_by(|(_, eval1, _), (_, eval2, _)| eval2.cmp(eval1));
 
         if board.get_current_side() == Side::Black {
             evaluations.reverse();
@@ -83,20 +194,80 @@ impl<E> Searcher for MinimaxSearch<E>
 where
     E: Evaluator,
 {
+    /// Like the default, but when the search is stopped before it's done,
+    /// returns the best move it found so far, if it got as far as finding
+    /// one
+    fn search(
+        &mut self,
+        board: &Board,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        match self.search_order(board) {
+            Ok(order) => order.first().cloned().ok_or("No moves possible"),
+            Err(SEARCH_ABORTED) => {
+                self.best_root_move.clone().ok_or(SEARCH_ABORTED)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     fn search_order(
-        &self,
+        &mut self,
         board: &Board,
     ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
-        self.search_order_impl(board, self.depth)
+        self.stats = SearchStats::default();
+        self.search_start = Instant::now();
+        self.best_root_move = None;
+        self.deadline =
+            self.limits.get_deadlines(self.search_start).map(|d| d.hard);
+
+        let result = self.search_order_impl(board, self.depth);
+        self.stats.elapsed = self.search_start.elapsed();
+
+        let (order, principal_variations) = result?
+            .into_iter()
+            .map(|(m, evaluation, line)| ((m, evaluation), line))
+            .unzip();
+        self.principal_variations = principal_variations;
+
+        Ok(order)
+    }
+
+    fn set_limits(&mut self, limits: &SearchLimits) {
+        // Our depth counts the plies searched after the first one
+        self.depth = limits.max_depth().saturating_sub(1).into();
+        self.limits = limits.clone();
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.observer = observer;
+    }
+
+    fn observer(&mut self) -> Option<&mut dyn SearchObserver> {
+        Some(self.observer.as_mut())
+    }
+
+    fn search_stats(&self) -> SearchStats {
+        self.stats.clone()
+    }
+
+    fn get_principal_variation(&self, root_move: &ChessMove) -> Vec<ChessMove> {
+        self.principal_variations
+            .iter()
+            .find(|line| line.first() == Some(root_move))
+            .cloned()
+            .unwrap_or_else(|| vec![root_move.clone()])
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::convert::TryInto;
+    use std::time::Duration;
 
     use crate::board::game::ChessResult;
+    use crate::evaluation::material::MaterialEvaluator;
     use crate::evaluation::result::ResultEvaluator;
+    use crate::search::test_utils::get_mate_in_two_board;
 
     use super::*;
 
@@ -130,17 +301,7 @@ mod tests {
 
     #[test]
     fn finds_mate_in_two() {
-        let board = Board::from_art(
-            ".k......\n\
-             ........\n\
-             ..K.....\n\
-             ..R.....\n\
-             ........\n\
-             ........\n\
-             ........\n\
-             ........\n",
-        )
-        .unwrap();
+        let board = get_mate_in_two_board();
 
         let evaluator = ResultEvaluator::new();
         let mut searcher = MinimaxSearch::new(evaluator);
@@ -152,4 +313,54 @@ mod tests {
             Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
         );
     }
+
+    #[test]
+    fn gives_up_after_deadline() {
+        let mut searcher = MinimaxSearch::new(ResultEvaluator::new());
+        searcher.set_limits(&SearchLimits::move_time(Duration::ZERO));
+
+        assert_eq!(searcher.search(&Board::default()), Err(SEARCH_ABORTED));
+    }
+
+    #[test]
+    fn returns_best_move_so_far_when_stopped() {
+        let mut searcher = MinimaxSearch::new(MaterialEvaluator::new());
+        let board = Board::default();
+
+        searcher.set_depth(3);
+        searcher.search(&board).unwrap();
+        let nodes = searcher.nodes_searched();
+
+        // Running out of nodes halfway through stops the search the same way
+        // a stop handle does, with some of the root moves searched
+        searcher.set_limits(&SearchLimits {
+            nodes: Some(nodes / 2),
+            ..SearchLimits::depth(3)
+        });
+        let (best_move, _) = searcher.search(&board).unwrap();
+        assert!(searcher.nodes_searched() < nodes);
+        assert!(board.generate_moves(true).unwrap().contains(&best_move));
+    }
+
+    #[test]
+    fn principal_variation_covers_search_depth() {
+        let mut searcher = MinimaxSearch::new(MaterialEvaluator::new());
+        searcher.set_depth(3);
+
+        let (pv, _) = searcher
+            .search_principal_variation(&Board::default())
+            .unwrap();
+        assert_eq!(pv.len(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_node_limit() {
+        let mut searcher = MinimaxSearch::new(MaterialEvaluator::new());
+        searcher.set_limits(&SearchLimits {
+            nodes: Some(100),
+            ..SearchLimits::depth(3)
+        });
+
+        assert_eq!(searcher.search(&Board::default()), Err(SEARCH_ABORTED));
+    }
 }
@@ -1,11 +1,40 @@
 pub mod alphabeta;
+pub mod iterative_deepening;
+pub mod lazy_smp;
+pub mod limits;
+pub mod mcts;
 pub mod minimax;
+pub mod move_ordering;
+pub mod observer;
+pub mod ponder;
+pub mod proof_number;
+pub mod rng;
+pub mod root_split;
+pub mod skill;
+pub mod stats;
+pub mod stop;
+pub mod time_manager;
+pub mod transposition_table;
+
+#[cfg(test)]
+mod test_utils;
+
+use std::{ops::ControlFlow, time::Instant};
 
 use crate::{
     board::{chess_move::ChessMove, Board},
-    evaluation::evaluation_result::Evaluation,
+    evaluation::evaluation_result::{Depth, Evaluation},
+};
+
+use self::{
+    limits::SearchLimits, observer::SearchObserver, stats::SearchStats,
+    stop::StopHandle, time_manager::Deadlines,
 };
 
+/// Error returned by a search that was abandoned before it could finish, for
+/// example because its deadline passed
+pub const SEARCH_ABORTED: &str = "Search was aborted";
+
 /// A searcher is a type that can look through the move tree and figure out a
 /// "good" move for the current position. It may maintain a cache internally of
 /// move evaluations, and so shouldn't be discarded cheaply.
@@ -13,7 +42,7 @@ pub trait Searcher {
     /// Get the best move in the position
     /// Has a default implementation that returns the top result of `search_order`
     fn search(
-        &self,
+        &mut self,
         board: &Board,
     ) -> Result<(ChessMove, Evaluation), &'static str> {
         let possible_moves = self.search_order(board)?;
@@ -24,7 +53,155 @@ pub trait Searcher {
     /// Returns a list of moves ranked from best to worst
     /// along with a centipawn evaluation of each move
     fn search_order(
-        &self,
+        &mut self,
         board: &Board,
     ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str>;
+
+    /// Sets when following calls to `search_order` stop, replacing the
+    /// limits set before
+    ///
+    /// Searches look `limits.max_depth()` plies ahead, and give up and return
+    /// `SEARCH_ABORTED` once the hard deadline passes, they've looked at
+    /// `limits.nodes` nodes, or the stop handle is stopped. Time limits count
+    /// from the start of every search.
+    ///
+    /// To get the best move found so far instead of an error, search with
+    /// `search_with_limits` instead.
+    fn set_limits(&mut self, limits: &SearchLimits);
+
+    /// Sets how many plies ahead `search_order` looks, without any other
+    /// limits
+    fn set_depth(&mut self, depth: Depth) {
+        self.set_limits(&SearchLimits::depth(depth));
+    }
+
+    /// Tells the searcher which order to try the moves at the root in, for
+    /// example the order found by a shallower search. Moves that aren't in
+    /// `moves` are tried after the ones that are.
+    ///
+    /// Searchers that don't benefit from move ordering can ignore this.
+    fn set_root_move_order(&mut self, _moves: Vec<ChessMove>) {}
+
+    /// Sets how many of the best root moves `search_order` evaluates exactly.
+    /// The evaluations of the other moves can be bounds, only showing that
+    /// they're worse than the moves before them.
+    ///
+    /// Searchers that always evaluate every root move exactly, or never do,
+    /// can ignore this.
+    fn set_multi_pv(&mut self, _lines: usize) {}
+
+    /// Sets the observer that gets told about the progress of following
+    /// searches
+    ///
+    /// Searchers that don't report their progress can ignore this.
+    fn set_observer(&mut self, _observer: Box<dyn SearchObserver>) {}
+
+    /// The observer set through `set_observer`, if the searcher keeps one
+    fn observer(&mut self) -> Option<&mut dyn SearchObserver> {
+        None
+    }
+
+    /// What the last search did, see `SearchStats`
+    ///
+    /// Searchers that don't keep track of this return empty statistics.
+    fn search_stats(&self) -> SearchStats {
+        SearchStats::default()
+    }
+
+    /// How many nodes the last search looked at
+    fn nodes_searched(&self) -> u64 {
+        self.search_stats().nodes
+    }
+
+    /// Same as `search`, but also returns what the search did
+    fn search_with_stats(
+        &mut self,
+        board: &Board,
+    ) -> Result<(ChessMove, Evaluation, SearchStats), &'static str> {
+        let (best_move, evaluation) = self.search(board)?;
+
+        Ok((best_move, evaluation, self.search_stats()))
+    }
+
+    /// The line of play the last completed search expects after `root_move`,
+    /// starting with `root_move` itself. Lines can be shorter than the search
+    /// depth, for example when part of the search was skipped thanks to a
+    /// cached result.
+    ///
+    /// Searchers that don't keep track of lines only return `root_move`.
+    fn get_principal_variation(&self, root_move: &ChessMove) -> Vec<ChessMove> {
+        vec![root_move.clone()]
+    }
+
+    /// Get the best line of play in the position, starting with the best
+    /// move, along with its evaluation
+    fn search_principal_variation(
+        &mut self,
+        board: &Board,
+    ) -> Result<(Vec<ChessMove>, Evaluation), &'static str> {
+        let (best_move, evaluation) = self.search(board)?;
+
+        Ok((self.get_principal_variation(&best_move), evaluation))
+    }
+
+    /// Tells the searcher that following searches are for a new game, so
+    /// anything it has cached about earlier positions is no longer useful
+    fn new_game(&mut self) {}
+
+    /// Get the best move in the position, searching iteratively deeper until
+    /// one of `limits` is reached. See `iterative_deepening::deepen`.
+    fn search_with_limits(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        iterative_deepening::deepen(self, board, limits, |_| {
+            ControlFlow::Continue(())
+        })?
+        .first()
+        .cloned()
+        .ok_or("No moves possible")
+    }
+
+    /// Get the best move in the position, within the given deadlines
+    ///
+    /// Searches iteratively deeper, until the soft deadline passes or the hard
+    /// deadline cuts an iteration short.
+    fn search_timed(
+        &mut self,
+        board: &Board,
+        deadlines: Deadlines,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        self.search_with_limits(board, &SearchLimits::deadlines(deadlines))
+    }
+}
+
+/// Returns `SEARCH_ABORTED` if `deadline` has passed
+pub fn check_deadline(deadline: Option<Instant>) -> Result<(), &'static str> {
+    if deadline.is_some_and(|d| Instant::now() >= d) {
+        Err(SEARCH_ABORTED)
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns `SEARCH_ABORTED` if `stop` has been stopped
+pub fn check_stop(stop: Option<&StopHandle>) -> Result<(), &'static str> {
+    if stop.is_some_and(|s| s.is_stopped()) {
+        Err(SEARCH_ABORTED)
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns `SEARCH_ABORTED` if `nodes` has reached `limit`
+pub fn check_node_limit(
+    nodes: u64,
+    limit: Option<u64>,
+) -> Result<(), &'static str> {
+    if limit.is_some_and(|l| nodes >= l) {
+        Err(SEARCH_ABORTED)
+    } else {
+        Ok(())
+    }
 }
@@ -0,0 +1,350 @@
+use crate::{
+    board::{chess_move::ChessMove, piece::Side, square::Square, Board},
+    evaluation::{evaluation_result::Depth, material::PieceValues},
+};
+
+/// How `AlphabetaSearch` decides which moves to search first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MoveOrdering {
+    /// Uses `MoveOrderer`, which learns from the search as it goes. This is
+    /// the default.
+    #[default]
+    Heuristics,
+
+    /// Evaluates the position after every move with the move sorting
+    /// evaluator, and searches the best looking ones first
+    Evaluator,
+}
+
+// Moves are scored in bands, so that every kind of move is tried before the
+// next. History scores are kept below `MAX_HISTORY`, so quiet moves stay
+// between the countermoves and the captures that lose material.
+const GOOD_CAPTURE_SCORE: i32 = 4_000_000;
+const KILLER_SCORE: i32 = 3_000_000;
+const COUNTERMOVE_SCORE: i32 = 2_000_000;
+const MAX_HISTORY: i32 = 1_000_000;
+const BAD_CAPTURE_SCORE: i32 = -2_000_000;
+
+/// How many killer moves are remembered for every ply
+const KILLERS_PER_PLY: usize = 2;
+
+/// Decides the order moves are searched in, based on what they capture and
+/// on which moves caused cutoffs earlier in the search
+///
+/// Captures that don't lose material come first, most valuable victim first.
+/// Then come the quiet moves, starting with the killer moves that caused a
+/// cutoff at the same ply elsewhere in the tree, and the countermove that
+/// caused a cutoff after the same move by the opponent. The other quiet moves
+/// are sorted by how often they caused cutoffs anywhere in the search, and
+/// captures that lose material come last.
+pub struct MoveOrderer {
+    killers: Vec<[Option<ChessMove>; KILLERS_PER_PLY]>,
+
+    // How many squares the boards the tables below are sized for have. They
+    // start out empty, and are sized when the first cutoff is recorded.
+    squares: usize,
+
+    // Indexed by side, then the squares the move is from and to, see
+    // `history_index`
+    history: Vec<i32>,
+
+    // Indexed by the squares the opponent's last move was from and to, as
+    // `from * squares + to`
+    countermoves: Vec<Option<ChessMove>>,
+}
+
+impl MoveOrderer {
+    pub fn new() -> Self {
+        MoveOrderer {
+            killers: vec![],
+            squares: 0,
+            history: vec![],
+            countermoves: vec![],
+        }
+    }
+
+    /// Forget everything, for example because a new game started
+    pub fn clear(&mut self) {
+        *self = MoveOrderer::new();
+    }
+
+    /// Marks the start of a search for a new position. Killer moves are
+    /// specific to the plies of the last search, so they're forgotten, and
+    /// the history is halved so that newer cutoffs count for more.
+    pub fn new_search(&mut self) {
+        self.killers.clear();
+        self.age_history();
+    }
+
+    /// How early `chess_move` should be searched in `board`, higher meaning
+    /// earlier. `ply` counts from the root of the search, and
+    /// `previous_move` is the move the opponent made to get to `board`.
+    pub fn score(
+        &self,
+        board: &Board,
+        chess_move: &ChessMove,
+        ply: usize,
+        previous_move: Option<&ChessMove>,
+        values: &PieceValues,
+    ) -> i32 {
+        if let Some(victim) = board.get_captured_piece(chess_move) {
+            let attacker = board
+                .get_moving_piece(chess_move)
+                .map_or(0, |p| values.get(p.piece_type));
+            let mvv_lva = 10 * values.get(victim.piece_type) - attacker;
+
+            return if board.see_with_values(chess_move, values) >= 0 {
+                GOOD_CAPTURE_SCORE + mvv_lva
+            } else {
+                BAD_CAPTURE_SCORE + mvv_lva
+            };
+        }
+
+        let killers = self.killers.get(ply);
+        if let Some(index) = killers
+            .and_then(|k| k.iter().position(|m| m.as_ref() == Some(chess_move)))
+        {
+            // The most recent killer goes first
+            return KILLER_SCORE - index as i32;
+        }
+
+        if previous_move.and_then(|m| self.get_countermove(board, m))
+            == Some(chess_move)
+        {
+            return COUNTERMOVE_SCORE;
+        }
+
+        let side = board.get_current_side();
+        get_squares(board, chess_move)
+            .and_then(|(from, to)| {
+                self.history.get(self.history_index(side, from, to))
+            })
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Remembers that `chess_move` caused a cutoff in `board`, `ply` plies
+    /// from the root and `depth` plies from the leaves. Only quiet moves are
+    /// remembered, since captures are ordered well without help.
+    pub fn record_cutoff(
+        &mut self,
+        board: &Board,
+        chess_move: &ChessMove,
+        ply: usize,
+        depth: Depth,
+        previous_move: Option<&ChessMove>,
+    ) {
+        if *chess_move == ChessMove::NullMove
+            || board.get_captured_piece(chess_move).is_some()
+        {
+            return;
+        }
+
+        self.fit_to(board);
+
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, Default::default());
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0].as_ref() != Some(chess_move) {
+            killers.rotate_right(1);
+            killers[0] = Some(chess_move.clone());
+        }
+
+        if let Some((from, to)) =
+            previous_move.and_then(|m| get_squares(board, m))
+        {
+            self.countermoves[from * self.squares + to] =
+                Some(chess_move.clone());
+        }
+
+        if let Some((from, to)) = get_squares(board, chess_move) {
+            // Cutoffs far from the leaves save more work, so they count more
+            let index = self.history_index(board.get_current_side(), from, to);
+            let bonus = i32::from(depth) * i32::from(depth);
+            self.history[index] += bonus;
+
+            if self.history[index] >= MAX_HISTORY {
+                self.age_history();
+            }
+        }
+    }
+
+    // Sizes the tables for `board`, throwing away what they had if they were
+    // for boards of another size
+    fn fit_to(&mut self, board: &Board) {
+        let squares = board.get_squares().len();
+        if self.squares != squares {
+            self.squares = squares;
+            self.history = vec![0; 2 * squares * squares];
+            self.countermoves = vec![None; squares * squares];
+        }
+    }
+
+    fn history_index(&self, side: Side, from: usize, to: usize) -> usize {
+        (side_index(side) * self.squares + from) * self.squares + to
+    }
+
+    fn get_countermove(
+        &self,
+        board: &Board,
+        previous_move: &ChessMove,
+    ) -> Option<&ChessMove> {
+        let (from, to) = get_squares(board, previous_move)?;
+        self.countermoves.get(from * self.squares + to)?.as_ref()
+    }
+
+    fn age_history(&mut self) {
+        self.history.iter_mut().for_each(|score| *score /= 2);
+    }
+}
+
+impl Default for MoveOrderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The indices of the squares a move on `board` is from and to, for moves
+/// that have them
+fn get_squares(
+    board: &Board,
+    chess_move: &ChessMove,
+) -> Option<(usize, usize)> {
+    match *chess_move {
+        ChessMove::SimpleMove(from, to) | ChessMove::EnPassant(from, to, _) => {
+            Some((square_index(board, from), square_index(board, to)))
+        }
+        ChessMove::Castling(_) | ChessMove::NullMove => None,
+    }
+}
+
+fn square_index(board: &Board, square: Square) -> usize {
+    usize::from(square.rank) * usize::from(board.get_width())
+        + usize::from(square.file)
+}
+
+fn side_index(side: Side) -> usize {
+    match side {
+        Side::White => 0,
+        Side::Black => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    fn sort_moves(
+        orderer: &MoveOrderer,
+        board: &Board,
+        previous_move: Option<&ChessMove>,
+    ) -> Vec<ChessMove> {
+        let values = PieceValues::default();
+        let mut moves = board.generate_moves(true).unwrap();
+        moves.sort_by_key(|m| {
+            -orderer.score(board, m, 0, previous_move, &values)
+        });
+        moves
+    }
+
+    fn chess_move(m: &str) -> ChessMove {
+        ChessMove::try_from(m).unwrap()
+    }
+
+    #[test]
+    fn orders_captures_by_value_and_exchange() {
+        let board = Board::from_art(
+            "....k...\n\
+             ....p...\n\
+             .r......\n\
+             ...N....\n\
+             .....q..\n\
+             ....P...\n\
+             ........\n\
+             ......K.\n",
+        )
+        .unwrap();
+
+        let moves = sort_moves(&MoveOrderer::new(), &board, None);
+
+        // The queen is taken first, with the least valuable piece first,
+        // then the rook. Taking the defended pawn comes after every quiet
+        // move.
+        assert_eq!(
+            moves[..3],
+            [chess_move("e3f4"), chess_move("d5f4"), chess_move("d5b6")]
+        );
+        assert_eq!(moves.last(), Some(&chess_move("d5e7")));
+    }
+
+    #[test]
+    fn killers_and_countermoves_come_before_other_quiet_moves() {
+        let board = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             R...K...\n",
+        )
+        .unwrap();
+        let previous_move = chess_move("e7e8");
+
+        let mut orderer = MoveOrderer::new();
+        orderer.record_cutoff(&board, &chess_move("a1a7"), 1, 3, None);
+        orderer.record_cutoff(&board, &chess_move("a1a8"), 0, 3, None);
+        orderer.record_cutoff(
+            &board,
+            &chess_move("e1d2"),
+            1,
+            3,
+            Some(&previous_move),
+        );
+        orderer.record_cutoff(&board, &chess_move("e1e2"), 0, 3, None);
+
+        let moves = sort_moves(&orderer, &board, Some(&previous_move));
+        assert_eq!(
+            moves[..4],
+            [
+                chess_move("e1e2"),
+                chess_move("a1a8"),
+                chess_move("e1d2"),
+                chess_move("a1a7"),
+            ]
+        );
+    }
+
+    #[test]
+    fn history_fades_between_searches() {
+        let board = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             R...K...\n",
+        )
+        .unwrap();
+        let values = PieceValues::default();
+        let m = chess_move("a1a5");
+
+        let mut orderer = MoveOrderer::new();
+        orderer.record_cutoff(&board, &m, 3, 4, None);
+        assert_eq!(orderer.score(&board, &m, 0, None, &values), 16);
+        assert_eq!(orderer.score(&board, &m, 3, None, &values), KILLER_SCORE);
+
+        orderer.new_search();
+        assert_eq!(orderer.score(&board, &m, 0, None, &values), 8);
+        assert_eq!(orderer.score(&board, &m, 3, None, &values), 8);
+
+        orderer.clear();
+        assert_eq!(orderer.score(&board, &m, 0, None, &values), 0);
+    }
+}
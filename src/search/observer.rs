@@ -0,0 +1,54 @@
+use crate::{
+    board::chess_move::ChessMove,
+    evaluation::evaluation_result::{Depth, Evaluation},
+};
+
+use super::{iterative_deepening::IterationInfo, stats::SearchStats};
+
+/// How many nodes a searcher looks at between calls to
+/// `SearchObserver::on_progress`
+pub const PROGRESS_INTERVAL: u64 = 4096;
+
+/// Gets told what a search is doing while it's running, for example to show
+/// progress to a user
+///
+/// Every method does nothing by default, so observers only need to implement
+/// the events they're interested in.
+///
+/// Observers have to be `Send`, so that searchers can be moved to other
+/// threads along with them.
+pub trait SearchObserver: Send {
+    /// Called after every completed iteration of an iterative search
+    fn on_iteration(&mut self, _info: &IterationInfo) {}
+
+    /// Called when a root move turns out better than the moves searched before
+    /// it at the same depth
+    fn on_new_best_move(
+        &mut self,
+        _chess_move: &ChessMove,
+        _evaluation: Evaluation,
+        _depth: Depth,
+    ) {
+    }
+
+    /// Called when the search starts looking at a root move. `move_number`
+    /// counts from 1.
+    fn on_current_move(
+        &mut self,
+        _chess_move: &ChessMove,
+        _move_number: usize,
+        _depth: Depth,
+    ) {
+    }
+
+    /// Called every `PROGRESS_INTERVAL` nodes, with the statistics of the
+    /// current search so far
+    fn on_progress(&mut self, _stats: &SearchStats) {}
+}
+
+/// An observer that ignores every event. Searchers use this until they're
+/// given another observer.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopObserver;
+
+impl SearchObserver for NoopObserver {}
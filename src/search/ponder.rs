@@ -0,0 +1,381 @@
+use std::{
+    ops::ControlFlow,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use crate::{
+    board::{chess_move::ChessMove, Board},
+    evaluation::evaluation_result::Evaluation,
+};
+
+use super::{
+    iterative_deepening::deepen, limits::SearchLimits, stop::StopHandle,
+    time_manager::Deadlines, Searcher,
+};
+
+/// What a search running on another thread sends back once it's done: the
+/// searcher, so it can be used again, and the move order it found
+type PonderResult<S> = (S, Result<Vec<(ChessMove, Evaluation)>, &'static str>);
+
+/// Searches on the opponent's time
+///
+/// After the engine plays a move, the line it expects contains the
+/// opponent's most likely reply, see `ponder_move`. While the opponent
+/// thinks, `start` searches the position after that reply on another thread,
+/// without any time limits. If the opponent plays the expected move,
+/// `ponder_hit` turns that into a normal timed search, which carries on from
+/// the iteration it was at, with everything the searcher has learned. If
+/// they play something else, `ponder_miss` throws the search away and
+/// searches the actual position instead.
+///
+/// This is what the UCI `go ponder` and `ponderhit` commands do.
+pub struct Ponderer<S> {
+    // The searcher, when it's not busy pondering on the other thread
+    searcher: Option<S>,
+    pondering: Option<Pondering<S>>,
+}
+
+// A search running on another thread
+struct Pondering<S> {
+    stop: StopHandle,
+    limits: SearchLimits,
+
+    // Set once the expected move was played, to when the search has to stop
+    hit: Arc<Mutex<Option<Deadlines>>>,
+
+    result: Receiver<PonderResult<S>>,
+}
+
+impl<S> Ponderer<S>
+where
+    S: Searcher + Send + 'static,
+{
+    pub fn new(searcher: S) -> Self {
+        Ponderer {
+            searcher: Some(searcher),
+            pondering: None,
+        }
+    }
+
+    /// The searcher, unless it's busy pondering
+    pub fn searcher(&mut self) -> Option<&mut S> {
+        self.searcher.as_mut()
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.is_some()
+    }
+
+    /// Searches `board` until one of `limits` is reached, like
+    /// `Searcher::search_with_limits`
+    pub fn search(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        self.searcher
+            .as_mut()
+            .ok_or("Already pondering")?
+            .search_with_limits(board, limits)
+    }
+
+    /// The reply to `best_move` the last search expects, to ponder on once
+    /// `best_move` has been played. `None` if the line stops after
+    /// `best_move`, or while pondering.
+    pub fn ponder_move(&self, best_move: &ChessMove) -> Option<ChessMove> {
+        let line = self.searcher.as_ref()?.get_principal_variation(best_move);
+        line.get(1).cloned()
+    }
+
+    /// Starts searching `board`, the position after the expected reply, on
+    /// another thread. The search ignores the time limits in `limits` until
+    /// `ponder_hit`, and the stop handle is replaced by the ponderer's own,
+    /// see `stop`.
+    pub fn start(
+        &mut self,
+        board: &Board,
+        limits: SearchLimits,
+    ) -> Result<(), &'static str> {
+        let mut searcher = self.searcher.take().ok_or("Already pondering")?;
+
+        let stop = StopHandle::new();
+        let hit: Arc<Mutex<Option<Deadlines>>> = Arc::new(Mutex::new(None));
+        let ponder_limits = SearchLimits {
+            move_time: None,
+            deadlines: None,
+            stop: Some(stop.clone()),
+            ..limits.clone()
+        };
+
+        let (sender, result) = mpsc::channel();
+        let board = board.clone();
+        let thread_hit = Arc::clone(&hit);
+        thread::spawn(move || {
+            // Once the expected move is played, iterations stop at the soft
+            // deadline like in any other timed search
+            let order = deepen(&mut searcher, &board, &ponder_limits, |_| {
+                match *thread_hit.lock().unwrap() {
+                    Some(d) if Instant::now() >= d.soft => {
+                        ControlFlow::Break(())
+                    }
+                    _ => ControlFlow::Continue(()),
+                }
+            });
+
+            // Nobody is waiting for the result if the ponderer was dropped
+            let _ = sender.send((searcher, order));
+        });
+
+        self.pondering = Some(Pondering {
+            stop,
+            limits,
+            hit,
+            result,
+        });
+        Ok(())
+    }
+
+    /// Tells the ponderer the expected move was played, so the search goes on
+    /// with the time limits it was started with, counting from now.
+    /// `deadlines` replaces the deadlines in those limits, since they
+    /// usually have to be worked out again from the clock at this point.
+    ///
+    /// Waits for the search to finish, and returns the best move.
+    pub fn ponder_hit(
+        &mut self,
+        deadlines: Option<Deadlines>,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        let pondering = self.pondering.as_ref().ok_or("Not pondering")?;
+
+        let limits = SearchLimits {
+            deadlines,
+            ..pondering.limits.clone()
+        };
+        let deadlines = limits.get_deadlines(Instant::now());
+        *pondering.hit.lock().unwrap() = deadlines;
+
+        self.finish(deadlines.map(|d| d.hard))
+    }
+
+    /// Tells the ponderer the opponent didn't play the expected move. Stops
+    /// the search, throwing away what it found, and searches `board`, the
+    /// actual position, instead.
+    pub fn ponder_miss(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        if let Some(pondering) = self.pondering.as_ref() {
+            pondering.stop.stop();
+        }
+        // Only the searcher matters, not the result of the search
+        let _ = self.finish(None);
+
+        self.search(board, limits)
+    }
+
+    /// Stops pondering right away, and returns the best move found so far
+    pub fn stop(&mut self) -> Result<(ChessMove, Evaluation), &'static str> {
+        let pondering = self.pondering.as_ref().ok_or("Not pondering")?;
+        pondering.stop.stop();
+
+        self.finish(None)
+    }
+
+    // Waits for the search to finish, stopping it at `hard_deadline` if it
+    // hasn't by then, and takes the searcher back
+    fn finish(
+        &mut self,
+        hard_deadline: Option<Instant>,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        let pondering = self.pondering.take().ok_or("Not pondering")?;
+
+        let received = match hard_deadline {
+            Some(deadline) => {
+                let timeout =
+                    deadline.saturating_duration_since(Instant::now());
+                match pondering.result.recv_timeout(timeout) {
+                    Err(RecvTimeoutError::Timeout) => {
+                        pondering.stop.stop();
+                        pondering.result.recv().ok()
+                    }
+                    received => received.ok(),
+                }
+            }
+            None => pondering.result.recv().ok(),
+        };
+
+        let (searcher, order) = received.ok_or("Pondering thread panicked")?;
+        self.searcher = Some(searcher);
+
+        order?.first().cloned().ok_or("No moves possible")
+    }
+}
+
+/// Stops the search if it's still pondering, so the thread doesn't keep
+/// running on its own
+impl<S> Drop for Ponderer<S> {
+    fn drop(&mut self) {
+        if let Some(pondering) = self.pondering.take() {
+            pondering.stop.stop();
+            let _ = pondering.result.recv();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::evaluation::evaluation_result::Depth;
+    use crate::evaluation::material::MaterialEvaluator;
+    use crate::search::alphabeta::AlphabetaSearch;
+    use crate::search::iterative_deepening::IterationInfo;
+    use crate::search::observer::SearchObserver;
+    use crate::search::test_utils::get_material_searcher;
+
+    use super::*;
+
+    // Remembers the depth of every completed iteration
+    struct IterationObserver(Arc<Mutex<Vec<Depth>>>);
+
+    impl SearchObserver for IterationObserver {
+        fn on_iteration(&mut self, info: &IterationInfo) {
+            self.0.lock().unwrap().push(info.depth);
+        }
+    }
+
+    fn get_ponderer(
+    ) -> Ponderer<AlphabetaSearch<MaterialEvaluator, MaterialEvaluator>> {
+        Ponderer::new(get_material_searcher())
+    }
+
+    // Plays `best_move` and the reply the ponderer expects to it
+    fn get_predicted_board<S>(
+        ponderer: &Ponderer<S>,
+        board: &Board,
+        best_move: &ChessMove,
+    ) -> Board
+    where
+        S: Searcher + Send + 'static,
+    {
+        let reply = ponderer.ponder_move(best_move).unwrap();
+
+        let mut board = board.clone();
+        board.make_move(best_move.clone(), true).unwrap();
+        board.make_move(reply, true).unwrap();
+        board
+    }
+
+    #[test]
+    fn ponder_hit_carries_on_with_the_search() {
+        let mut ponderer = get_ponderer();
+        let board = Board::default();
+        let (best_move, _) =
+            ponderer.search(&board, &SearchLimits::depth(3)).unwrap();
+        let predicted = get_predicted_board(&ponderer, &board, &best_move);
+
+        let depths = Arc::new(Mutex::new(vec![]));
+        ponderer
+            .searcher()
+            .unwrap()
+            .set_observer(Box::new(IterationObserver(Arc::clone(&depths))));
+
+        ponderer.start(&predicted, SearchLimits::depth(3)).unwrap();
+        assert!(ponderer.is_pondering());
+        assert!(ponderer.searcher().is_none());
+
+        let (chess_move, _) = ponderer.ponder_hit(None).unwrap();
+        assert!(predicted
+            .generate_moves(true)
+            .unwrap()
+            .contains(&chess_move));
+        assert!(!ponderer.is_pondering());
+
+        // Every iteration was searched once, rather than starting over
+        assert_eq!(*depths.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn ponder_hit_keeps_to_the_deadlines() {
+        let mut ponderer = get_ponderer();
+        ponderer
+            .start(&Board::default(), SearchLimits::infinite())
+            .unwrap();
+        thread::sleep(Duration::from_millis(20));
+
+        let start = Instant::now();
+        let deadlines = Deadlines {
+            soft: start + Duration::from_millis(50),
+            hard: start + Duration::from_millis(100),
+        };
+        ponderer.ponder_hit(Some(deadlines)).unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn ponder_miss_searches_the_actual_position() {
+        let mut ponderer = get_ponderer();
+        let board = Board::default();
+        let (best_move, _) =
+            ponderer.search(&board, &SearchLimits::depth(2)).unwrap();
+        let predicted = get_predicted_board(&ponderer, &board, &best_move);
+
+        let expected = ponderer.ponder_move(&best_move).unwrap();
+        ponderer
+            .start(&predicted, SearchLimits::infinite())
+            .unwrap();
+
+        // The opponent plays something else instead
+        let mut actual = board.clone();
+        actual.make_move(best_move, true).unwrap();
+        let unexpected = actual
+            .generate_moves(true)
+            .unwrap()
+            .into_iter()
+            .find(|m| *m != expected)
+            .unwrap();
+        actual.make_move(unexpected, true).unwrap();
+
+        let (chess_move, _) = ponderer
+            .ponder_miss(&actual, &SearchLimits::depth(2))
+            .unwrap();
+        assert!(actual.generate_moves(true).unwrap().contains(&chess_move));
+        assert!(!ponderer.is_pondering());
+    }
+
+    #[test]
+    fn stop_returns_the_best_move_so_far() {
+        let mut ponderer = get_ponderer();
+        let board = Board::default();
+        ponderer.start(&board, SearchLimits::infinite()).unwrap();
+        thread::sleep(Duration::from_millis(20));
+
+        let (chess_move, _) = ponderer.stop().unwrap();
+        assert!(board.generate_moves(true).unwrap().contains(&chess_move));
+    }
+
+    #[test]
+    fn can_only_ponder_once_at_a_time() {
+        let mut ponderer = get_ponderer();
+        let board = Board::default();
+        ponderer.start(&board, SearchLimits::infinite()).unwrap();
+
+        assert_eq!(
+            ponderer.start(&board, SearchLimits::infinite()),
+            Err("Already pondering")
+        );
+        assert_eq!(
+            ponderer.search(&board, &SearchLimits::depth(1)),
+            Err("Already pondering")
+        );
+        ponderer.stop().unwrap();
+        assert_eq!(ponderer.stop(), Err("Not pondering"));
+    }
+}
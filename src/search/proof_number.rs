@@ -0,0 +1,607 @@
+use std::{cmp::Reverse, time::Instant};
+
+use crate::{
+    board::{chess_move::ChessMove, game::ChessResult, piece::Side, Board},
+    evaluation::evaluation_result::{Depth, Evaluation},
+};
+
+use super::{
+    check_deadline, check_node_limit, check_stop,
+    limits::SearchLimits,
+    observer::{NoopObserver, SearchObserver, PROGRESS_INTERVAL},
+    stats::SearchStats,
+    Searcher,
+};
+
+/// Proof and disproof number of a node that can't be proven or disproven
+const INFINITY: u64 = u64::MAX;
+
+/// Finds forced mates with proof-number search
+///
+/// Proof-number search only tries to answer one question: can the attacker
+/// force a mate within a number of plies? Every position gets a proof number,
+/// the number of positions that would at least have to be proven mates for
+/// it to be a mate, and a disproof number, the same for proving it isn't.
+/// The search keeps expanding the position that would help the most, until
+/// the root is either proven or disproven. This finds deep mates with a much
+/// smaller tree than alpha-beta, but says nothing about positions without
+/// one.
+///
+/// The search looks for mates within 1 ply first, then 3, 5 and so on up to
+/// the search depth, so the first mate it proves is the shortest. Root moves
+/// that mate are evaluated as `Certain(Checkmate(..), plies)`, like the other
+/// searchers do, and every other move as `Estimate(0)`.
+///
+/// The attacker is the side to move, unless set otherwise with
+/// `set_attacker`, in which case the search proves that every move of the
+/// side to move gets mated.
+pub struct ProofNumberSearch {
+    depth: Depth,
+    limits: SearchLimits,
+
+    // The hard deadline of the current search, worked out from `limits` when
+    // it starts
+    deadline: Option<Instant>,
+    observer: Box<dyn SearchObserver>,
+    stats: SearchStats,
+
+    // When the current search started, for reporting progress
+    search_start: Instant,
+
+    // The line expected after every root move, as found by the last
+    // completed search
+    principal_variations: Vec<Vec<ChessMove>>,
+
+    attacker: Option<Side>,
+
+    // Every position in the tree, starting with the root
+    tree: Vec<Node>,
+
+    // Hash of the last position searched from and the attacker, and how many
+    // plies that search proved there's no mate within, so searching the same
+    // position deeper doesn't have to prove it again
+    last_root: Option<(u64, Side)>,
+    no_mate_within: Depth,
+}
+
+// A position in the tree
+struct Node {
+    chess_move: ChessMove,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    expanded: bool,
+    ply: Depth,
+
+    // Whether the attacker is to move here, so that proving one of the
+    // children proves this position, rather than having to prove all of them
+    attacker_to_move: bool,
+
+    proof: u64,
+    disproof: u64,
+
+    // For proven positions, how many plies the mate takes from here
+    mate_plies: Depth,
+}
+
+impl ProofNumberSearch {
+    pub fn new() -> Self {
+        ProofNumberSearch {
+            depth: 5,
+            limits: SearchLimits::depth(5),
+            deadline: None,
+            observer: Box::new(NoopObserver),
+            stats: SearchStats::default(),
+            search_start: Instant::now(),
+            principal_variations: vec![],
+            attacker: None,
+            tree: vec![],
+            last_root: None,
+            no_mate_within: 0,
+        }
+    }
+
+    /// Sets the side that's looking for a mate. `None`, the default, is
+    /// whichever side is to move.
+    pub fn set_attacker(&mut self, attacker: Option<Side>) -> &mut Self {
+        self.attacker = attacker;
+        self
+    }
+
+    /// Looks for a forced mate within the search depth, and returns the
+    /// mating line with its evaluation, or `None` if there isn't one
+    pub fn find_mate(
+        &mut self,
+        board: &Board,
+    ) -> Result<Option<(Vec<ChessMove>, Evaluation)>, &'static str> {
+        let (line, evaluation) = self.search_principal_variation(board)?;
+        let attacker = self.attacker.unwrap_or(board.get_current_side());
+
+        match evaluation {
+            Evaluation::Certain(ChessResult::Checkmate(side), _)
+                if side != attacker =>
+            {
+                Ok(Some((line, evaluation)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // Returns `SEARCH_ABORTED` once the search is stopped, or the deadline or
+    // the node limit is reached
+    fn check_limits(&self) -> Result<(), &'static str> {
+        check_stop(self.limits.stop.as_ref())?;
+        check_deadline(self.deadline)?;
+        check_node_limit(self.stats.nodes, self.limits.nodes)
+    }
+
+    // Tells the observer how the search is going, every `PROGRESS_INTERVAL`
+    // nodes
+    fn report_progress(&mut self) {
+        if self.stats.nodes.is_multiple_of(PROGRESS_INTERVAL) {
+            self.stats.elapsed = self.search_start.elapsed();
+            self.observer.on_progress(&self.stats);
+        }
+    }
+
+    // Builds a new tree from `board`, and expands it until it's proven or
+    // disproven that `attacker` can mate within `max_plies`. Returns whether
+    // they can.
+    fn prove(
+        &mut self,
+        board: &Board,
+        attacker: Side,
+        max_plies: Depth,
+    ) -> Result<bool, &'static str> {
+        self.tree.clear();
+        self.add_node(board, ChessMove::NullMove, None, attacker, max_plies)?;
+
+        while self.tree[0].proof != 0 && self.tree[0].disproof != 0 {
+            self.check_limits()?;
+
+            let (index, node_board) = self.most_proving_node(board)?;
+            self.expand(index, &node_board, attacker, max_plies)?;
+
+            let mut parent = Some(index);
+            while let Some(index) = parent {
+                self.update_numbers(index);
+                parent = self.tree[index].parent;
+            }
+        }
+
+        Ok(self.tree[0].proof == 0)
+    }
+
+    // Adds the position `board`, reached with `chess_move`, to the tree. Its
+    // numbers start out at 1, unless the game is over or it's too deep to be
+    // mate in time.
+    fn add_node(
+        &mut self,
+        board: &Board,
+        chess_move: ChessMove,
+        parent: Option<usize>,
+        attacker: Side,
+        max_plies: Depth,
+    ) -> Result<usize, &'static str> {
+        let ply = parent.map_or(0, |p| self.tree[p].ply + 1);
+        self.stats.count_node(usize::from(ply));
+        self.report_progress();
+
+        let (proof, disproof) = match board.get_game_result()? {
+            Some(ChessResult::Checkmate(side)) if side != attacker => {
+                (0, INFINITY)
+            }
+            Some(_) => (INFINITY, 0),
+            None if ply >= max_plies => (INFINITY, 0),
+            None => (1, 1),
+        };
+
+        self.tree.push(Node {
+            chess_move,
+            parent,
+            children: vec![],
+            expanded: false,
+            ply,
+            attacker_to_move: board.get_current_side() == attacker,
+            proof,
+            disproof,
+            mate_plies: 0,
+        });
+
+        Ok(self.tree.len() - 1)
+    }
+
+    // Follows the tree down from the root to the position that would help
+    // the most to prove or disprove the root, always taking the move with
+    // the smallest number that matters to the side to move. Returns the
+    // position's index and the board there.
+    fn most_proving_node(
+        &self,
+        root: &Board,
+    ) -> Result<(usize, Board), &'static str> {
+        let mut board = root.clone();
+        let mut index = 0;
+
+        while self.tree[index].expanded {
+            let node = &self.tree[index];
+            let children = node.children.iter();
+
+            // Out of equally good moves, the first one is taken
+            let next = if node.attacker_to_move {
+                children.min_by_key(|&&c| self.tree[c].proof)
+            } else {
+                children.min_by_key(|&&c| self.tree[c].disproof)
+            };
+
+            index = *next.ok_or("Expanded position without moves")?;
+            board.make_move(self.tree[index].chess_move.clone(), false)?;
+        }
+
+        Ok((index, board))
+    }
+
+    // Adds the positions after every move in `board` to the tree, as
+    // children of `index`
+    fn expand(
+        &mut self,
+        index: usize,
+        board: &Board,
+        attacker: Side,
+        max_plies: Depth,
+    ) -> Result<(), &'static str> {
+        for m in board.generate_moves(true)? {
+            let mut moved_board = board.clone();
+
+            // This can't fail, because it was generated by movegen
+            moved_board.make_move(m.clone(), false).unwrap();
+
+            let child = self.add_node(
+                &moved_board,
+                m,
+                Some(index),
+                attacker,
+                max_plies,
+            )?;
+            self.tree[index].children.push(child);
+        }
+
+        self.tree[index].expanded = true;
+        Ok(())
+    }
+
+    // Works out the numbers of an expanded position from its children's
+    // numbers. The attacker only needs one move to mate, but has to disprove
+    // every move to give up, and it's the other way around for the defender.
+    fn update_numbers(&mut self, index: usize) {
+        let node = &self.tree[index];
+        let children = node.children.iter().map(|&c| &self.tree[c]);
+        let sum = |numbers: &mut dyn Iterator<Item = u64>| {
+            numbers.fold(0, u64::saturating_add)
+        };
+
+        let (proof, disproof, mate_plies) = if node.attacker_to_move {
+            let proof = children.clone().map(|c| c.proof).min();
+            let disproof = sum(&mut children.clone().map(|c| c.disproof));
+            let mate_plies = children
+                .filter(|c| c.proof == 0)
+                .map(|c| c.mate_plies)
+                .min();
+            (proof, disproof, mate_plies)
+        } else {
+            let proof = sum(&mut children.clone().map(|c| c.proof));
+            let disproof = children.clone().map(|c| c.disproof).min();
+            let mate_plies = children.map(|c| c.mate_plies).max();
+            (Some(proof), disproof.unwrap_or(INFINITY), mate_plies)
+        };
+
+        let node = &mut self.tree[index];
+        node.proof = proof.unwrap_or(INFINITY);
+        node.disproof = disproof;
+        if node.proof == 0 {
+            node.mate_plies = mate_plies.map_or(0, |p| p + 1);
+        }
+    }
+
+    // The mating line after the proven position `index`, starting with the
+    // move leading to it. The attacker takes the fastest mate, and the
+    // defender holds out the longest.
+    fn mating_line(&self, mut index: usize) -> Vec<ChessMove> {
+        let mut line = vec![self.tree[index].chess_move.clone()];
+
+        loop {
+            let node = &self.tree[index];
+            let proven =
+                node.children.iter().filter(|&&c| self.tree[c].proof == 0);
+            let next = if node.attacker_to_move {
+                proven.min_by_key(|&&c| self.tree[c].mate_plies)
+            } else {
+                proven.max_by_key(|&&c| self.tree[c].mate_plies)
+            };
+
+            match next {
+                Some(&next) => {
+                    line.push(self.tree[next].chess_move.clone());
+                    index = next;
+                }
+                None => return line,
+            }
+        }
+    }
+}
+
+impl Default for ProofNumberSearch {
+    fn default() -> Self {
+        ProofNumberSearch::new()
+    }
+}
+
+impl Searcher for ProofNumberSearch {
+    fn search_order(
+        &mut self,
+        board: &Board,
+    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
+        self.stats = SearchStats::default();
+        self.search_start = Instant::now();
+        self.deadline =
+            self.limits.get_deadlines(self.search_start).map(|d| d.hard);
+
+        let side = board.get_current_side();
+        let attacker = self.attacker.unwrap_or(side);
+        let moves = board.generate_moves(true)?;
+
+        if moves.is_empty() {
+            let evaluation = match board.get_game_result()? {
+                Some(result) => Evaluation::Certain(result, 0),
+                None => Evaluation::Estimate(0),
+            };
+            return Ok(vec![(ChessMove::NullMove, evaluation)]);
+        }
+
+        let root = (board.get_hash(), attacker);
+        if self.last_root != Some(root) {
+            self.last_root = Some(root);
+            self.no_mate_within = 0;
+        }
+
+        // Mates are always delivered by the attacker's moves, so they take an
+        // odd number of plies when the attacker is to move, and an even one
+        // otherwise
+        let mut plies = if attacker == side { 1 } else { 2 };
+        while plies <= self.no_mate_within {
+            plies += 2;
+        }
+
+        self.tree.clear();
+        let mut result = Ok(());
+        while plies <= self.depth {
+            match self.prove(board, attacker, plies) {
+                Ok(true) => break,
+                Ok(false) => self.no_mate_within = plies,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+            plies = match plies.checked_add(2) {
+                Some(plies) => plies,
+                None => break,
+            };
+        }
+        self.stats.elapsed = self.search_start.elapsed();
+        result?;
+
+        // Root moves that weren't proven to mate, including all of them when
+        // there was no tree to search, aren't known to be anything
+        let mut searched_moves = moves
+            .into_iter()
+            .map(|m| {
+                let proven_child = self.tree.first().and_then(|root| {
+                    root.children.iter().copied().find(|&c| {
+                        self.tree[c].chess_move == m && self.tree[c].proof == 0
+                    })
+                });
+
+                match proven_child {
+                    Some(child) => {
+                        let plies = self.tree[child].mate_plies + 1;
+                        let result = ChessResult::Checkmate(attacker.flip());
+                        let evaluation = Evaluation::Certain(result, plies);
+                        (m, evaluation, self.mating_line(child))
+                    }
+                    None => (m.clone(), Evaluation::Estimate(0), vec![m]),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // The sort is stable, so moves that are equally good stay in the
+        // order they were generated in
+        searched_moves.sort_by_key(|(_, evaluation, _)| {
+            Reverse(evaluation.relative_to(side))
+        });
+
+        let (order, principal_variations) = searched_moves
+            .into_iter()
+            .map(|(m, evaluation, line)| ((m, evaluation), line))
+            .unzip();
+        self.principal_variations = principal_variations;
+
+        Ok(order)
+    }
+
+    /// The depth is how many plies long the mates the search looks for can be
+    /// at most
+    fn set_limits(&mut self, limits: &SearchLimits) {
+        self.depth = limits.max_depth();
+        self.limits = limits.clone();
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.observer = observer;
+    }
+
+    fn observer(&mut self) -> Option<&mut dyn SearchObserver> {
+        Some(self.observer.as_mut())
+    }
+
+    fn search_stats(&self) -> SearchStats {
+        self.stats.clone()
+    }
+
+    fn get_principal_variation(&self, root_move: &ChessMove) -> Vec<ChessMove> {
+        self.principal_variations
+            .iter()
+            .find(|line| line.first() == Some(root_move))
+            .cloned()
+            .unwrap_or_else(|| vec![root_move.clone()])
+    }
+
+    fn new_game(&mut self) {
+        self.tree.clear();
+        self.last_root = None;
+        self.no_mate_within = 0;
+        self.principal_variations.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::test_utils::{
+        get_mate_in_four_board, get_mate_in_two_board, get_mate_searcher,
+    };
+    use crate::search::SEARCH_ABORTED;
+
+    use super::*;
+
+    // Plays out `line` from `board`, and returns how the game ended
+    fn play_line(board: &Board, line: &[ChessMove]) -> Option<ChessResult> {
+        let mut board = board.clone();
+        for m in line {
+            board.make_move(m.clone(), true).unwrap();
+        }
+        board.get_game_result().unwrap()
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        let board = get_mate_in_two_board();
+        let mut searcher = ProofNumberSearch::new();
+
+        let (line, evaluation) = searcher.find_mate(&board).unwrap().unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
+        );
+        assert_eq!(line.len(), 3);
+        assert_eq!(
+            play_line(&board, &line),
+            Some(ChessResult::Checkmate(Side::Black))
+        );
+    }
+
+    #[test]
+    fn agrees_with_alphabeta() {
+        let board = get_mate_in_two_board();
+
+        let mut alphabeta = get_mate_searcher();
+        alphabeta.set_depth(4);
+        let (_, alphabeta_evaluation) = alphabeta.search(&board).unwrap();
+
+        let mut searcher = ProofNumberSearch::new();
+        let (_, evaluation) = searcher.search(&board).unwrap();
+
+        assert_eq!(evaluation, alphabeta_evaluation);
+    }
+
+    #[test]
+    fn finds_mate_in_four() {
+        let board = get_mate_in_four_board();
+        let mut searcher = ProofNumberSearch::new();
+        searcher.set_depth(9);
+
+        let (line, evaluation) = searcher.find_mate(&board).unwrap().unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 7)
+        );
+        assert_eq!(line.len(), 7);
+        assert_eq!(
+            play_line(&board, &line),
+            Some(ChessResult::Checkmate(Side::Black))
+        );
+    }
+
+    #[test]
+    fn disproves_mates_that_arent_there() {
+        let board = Board::default();
+        let mut searcher = ProofNumberSearch::new();
+        searcher.set_depth(3);
+
+        assert_eq!(searcher.find_mate(&board).unwrap(), None);
+
+        let order = searcher.search_order(&board).unwrap();
+        assert_eq!(order.len(), 20);
+        assert!(order.iter().all(|(_, e)| *e == Evaluation::Estimate(0)));
+    }
+
+    #[test]
+    fn proves_the_side_to_move_gets_mated() {
+        let mut board = get_mate_in_two_board();
+        let mut searcher = ProofNumberSearch::new();
+        let (line, _) = searcher.find_mate(&board).unwrap().unwrap();
+        board.make_move(line[0].clone(), true).unwrap();
+
+        searcher.set_attacker(Some(Side::White));
+        let (defence, evaluation) =
+            searcher.find_mate(&board).unwrap().unwrap();
+
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 2)
+        );
+        assert_eq!(defence.len(), 2);
+
+        // Every move gets mated, so they're all certain
+        let order = searcher.search_order(&board).unwrap();
+        assert!(order
+            .iter()
+            .all(|(_, e)| matches!(e, Evaluation::Certain(..))));
+    }
+
+    #[test]
+    fn searches_up_to_mate_limit() {
+        let mut searcher = ProofNumberSearch::new();
+
+        let (_, evaluation) = searcher
+            .search_with_limits(
+                &get_mate_in_two_board(),
+                &SearchLimits::mate(2),
+            )
+            .unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
+        );
+
+        // Mate in two can't be found when only looking for mate in one
+        let (_, evaluation) = searcher
+            .search_with_limits(
+                &get_mate_in_two_board(),
+                &SearchLimits::mate(1),
+            )
+            .unwrap();
+        assert_eq!(evaluation, Evaluation::Estimate(0));
+    }
+
+    #[test]
+    fn gives_up_after_node_limit() {
+        let mut searcher = ProofNumberSearch::new();
+        searcher.set_limits(&SearchLimits {
+            nodes: Some(100),
+            ..SearchLimits::depth(5)
+        });
+
+        assert_eq!(
+            searcher.search(&get_mate_in_four_board()),
+            Err(SEARCH_ABORTED)
+        );
+    }
+}
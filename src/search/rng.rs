@@ -0,0 +1,31 @@
+/// A xorshift random number generator, which is plenty for picking moves. It's
+/// seeded, so that searches can be repeated exactly.
+#[derive(Clone, Copy, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift gets stuck on zero
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A random number from 0 up to, but not including, `n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Whether something that happens with `probability`, between 0 and 1,
+    /// happens this time
+    pub fn chance(&mut self, probability: f64) -> bool {
+        // The top 53 bits fit exactly in the mantissa of an f64
+        let fraction = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        fraction < probability
+    }
+}
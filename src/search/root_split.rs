@@ -0,0 +1,438 @@
+use std::{
+    cmp::Reverse,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use crate::{
+    board::{chess_move::ChessMove, piece::Side, Board},
+    evaluation::{
+        evaluation_result::{Depth, Evaluation},
+        Evaluator,
+    },
+};
+
+use super::{
+    alphabeta::{AlphabetaSearch, SearchedMove},
+    limits::SearchLimits,
+    observer::{NoopObserver, SearchObserver},
+    stats::SearchStats,
+    Searcher,
+};
+
+/// Searches the root moves in parallel, every thread taking the next move
+/// nobody has searched yet
+///
+/// This is meant for analysing positions on machines with many cores, where
+/// getting through the whole search sooner matters more than finding a good
+/// move early. For playing games, see `LazySmpSearch`.
+///
+/// The first root move is searched on its own to get an evaluation to beat,
+/// then the threads split up the rest. Every thread has its own
+/// `AlphabetaSearch`, and they share one transposition table and the best
+/// evaluation found so far, which root moves are searched against. As with
+/// `AlphabetaSearch`, only the best move gets an exact evaluation, and the
+/// others are bounds.
+///
+/// How much the other moves get cut off depends on the order the threads
+/// finish them in, so their bounds can change from one search to the next.
+pub struct RootSplitSearch<ME, PE> {
+    searcher: AlphabetaSearch<ME, PE>,
+    threads: usize,
+    observer: Box<dyn SearchObserver>,
+    root_move_order: Vec<ChessMove>,
+    stats: SearchStats,
+
+    // What the helpers did during the last search, which `stats` includes
+    helper_stats: SearchStats,
+
+    // The line expected after every root move, as found by the last
+    // completed search
+    principal_variations: Vec<Vec<ChessMove>>,
+}
+
+// What the threads share while searching the root
+struct RootState<'a> {
+    best: Evaluation,
+    observer: &'a mut dyn SearchObserver,
+}
+
+impl<ME, PE> RootSplitSearch<ME, PE>
+where
+    ME: Evaluator + Clone + Send,
+    PE: Evaluator + Clone + Send,
+{
+    /// Searches with `searcher`'s settings, on as many threads as the machine
+    /// has cores
+    pub fn new(searcher: AlphabetaSearch<ME, PE>) -> Self {
+        RootSplitSearch {
+            searcher,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            observer: Box::new(NoopObserver),
+            root_move_order: vec![],
+            stats: SearchStats::default(),
+            helper_stats: SearchStats::default(),
+            principal_variations: vec![],
+        }
+    }
+
+    /// Sets how many threads search at once, counting the one searches are
+    /// started from. At least one thread is always used.
+    pub fn set_threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// The searcher whose settings every thread uses, for example to change
+    /// them. The other threads copy them at the start of every search.
+    ///
+    /// MultiPV isn't supported, and node limits are for every thread on its
+    /// own.
+    pub fn searcher_mut(&mut self) -> &mut AlphabetaSearch<ME, PE> {
+        &mut self.searcher
+    }
+
+    // The root moves in the order they're searched in, the ones we were told
+    // about first
+    fn root_moves(
+        &self,
+        board: &Board,
+    ) -> Result<Vec<ChessMove>, &'static str> {
+        let mut moves = board.generate_moves(true)?;
+
+        // The sort is stable, so moves we weren't told about keep their
+        // relative order
+        let root_move_order = &self.root_move_order;
+        moves.sort_by_key(|m| {
+            root_move_order
+                .iter()
+                .position(|r| r == m)
+                .unwrap_or(usize::MAX)
+        });
+
+        Ok(moves)
+    }
+}
+
+// Searches root moves until there are none left, taking the index of the
+// next one from `next` every time. Returns every move it searched, along with
+// its index in `moves`.
+fn search_root_moves<ME, PE>(
+    searcher: &mut AlphabetaSearch<ME, PE>,
+    board: &Board,
+    depth: Depth,
+    moves: &[ChessMove],
+    next: &AtomicUsize,
+    state: &Mutex<RootState>,
+) -> Result<Vec<(usize, SearchedMove)>, &'static str>
+where
+    ME: Evaluator,
+    PE: Evaluator,
+{
+    let side = board.get_current_side();
+    let mut searched_moves = vec![];
+
+    loop {
+        let index = next.fetch_add(1, Ordering::Relaxed);
+        let chess_move = match moves.get(index) {
+            Some(m) => m,
+            None => return Ok(searched_moves),
+        };
+
+        let alpha = {
+            let mut state = state.lock().unwrap();
+            state.observer.on_current_move(chess_move, index + 1, depth);
+            state.best
+        };
+
+        let searched_move =
+            searcher.search_root_move(board, chess_move, alpha)?;
+
+        // Another thread may have found a better move in the meantime, so
+        // this compares against the best evaluation as it is now
+        {
+            let mut state = state.lock().unwrap();
+            let evaluation = searched_move.1;
+            if evaluation.relative_to(side) > state.best.relative_to(side) {
+                state.best = evaluation;
+                state
+                    .observer
+                    .on_new_best_move(chess_move, evaluation, depth);
+            }
+        }
+
+        searched_moves.push((index, searched_move));
+    }
+}
+
+impl<ME, PE> Searcher for RootSplitSearch<ME, PE>
+where
+    ME: Evaluator + Clone + Send,
+    PE: Evaluator + Clone + Send,
+{
+    fn search_order(
+        &mut self,
+        board: &Board,
+    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
+        let search_start = Instant::now();
+        self.stats = SearchStats::default();
+        self.helper_stats = SearchStats::default();
+
+        let moves = self.root_moves(board)?;
+        if moves.is_empty() {
+            // Lets the searcher evaluate the final position
+            let result = self.searcher.search_order(board);
+            self.stats = self.searcher.search_stats();
+            return result;
+        }
+
+        let mut helpers = (1..self.threads)
+            .map(|_| {
+                let mut helper = self.searcher.helper();
+                helper.start_search(board);
+                helper
+            })
+            .collect::<Vec<_>>();
+
+        let side = board.get_current_side();
+        let worst = match side {
+            Side::White => Evaluation::BEST_FOR_BLACK,
+            Side::Black => Evaluation::BEST_FOR_WHITE,
+        };
+        let depth = self.searcher.depth();
+        let searcher = &mut self.searcher;
+        let state = Mutex::new(RootState {
+            best: worst,
+            observer: self.observer.as_mut(),
+        });
+
+        // The first move is searched by itself, so that the others have an
+        // evaluation to beat from the start
+        searcher.start_search(board);
+        let first = AtomicUsize::new(0);
+        let first_result = search_root_moves(
+            searcher,
+            board,
+            depth,
+            &moves[..1],
+            &first,
+            &state,
+        );
+
+        let next = AtomicUsize::new(1);
+        let results = first_result.and_then(|first_moves| {
+            thread::scope(|scope| {
+                let handles = helpers
+                    .iter_mut()
+                    .map(|helper| {
+                        let (moves, next, state) = (&moves, &next, &state);
+                        scope.spawn(move || {
+                            search_root_moves(
+                                helper, board, depth, moves, next, state,
+                            )
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut searched_moves = first_moves;
+                let main_result = search_root_moves(
+                    searcher, board, depth, &moves, &next, &state,
+                );
+
+                // Every thread has to finish before we can return, even if
+                // one of them already failed
+                let helper_results = handles
+                    .into_iter()
+                    .map(|h| h.join().unwrap())
+                    .collect::<Vec<_>>();
+
+                searched_moves.extend(main_result?);
+                for result in helper_results {
+                    searched_moves.extend(result?);
+                }
+                Ok(searched_moves)
+            })
+        });
+
+        for helper in &helpers {
+            self.helper_stats.merge(&helper.search_stats());
+        }
+        self.stats = self.searcher.search_stats();
+        self.stats.merge(&self.helper_stats);
+        self.stats.elapsed = search_start.elapsed();
+        let mut searched_moves = results?;
+
+        // Moves that are equally good stay in the order they'd be searched in
+        // by a single thread
+        searched_moves.sort_by_key(|(index, _)| *index);
+        searched_moves
+            .sort_by_key(|(_, (_, eval, _))| Reverse(eval.relative_to(side)));
+
+        let (order, principal_variations) = searched_moves
+            .into_iter()
+            .map(|(_, (m, evaluation, line))| ((m, evaluation), line))
+            .unzip();
+        self.principal_variations = principal_variations;
+
+        Ok(order)
+    }
+
+    fn set_limits(&mut self, limits: &SearchLimits) {
+        self.searcher.set_limits(limits);
+    }
+
+    fn set_root_move_order(&mut self, moves: Vec<ChessMove>) {
+        self.root_move_order = moves;
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.observer = observer;
+    }
+
+    fn observer(&mut self) -> Option<&mut dyn SearchObserver> {
+        Some(self.observer.as_mut())
+    }
+
+    /// What every thread did during the last search, added up. The time it
+    /// took is how long the whole search took.
+    fn search_stats(&self) -> SearchStats {
+        self.stats.clone()
+    }
+
+    fn get_principal_variation(&self, root_move: &ChessMove) -> Vec<ChessMove> {
+        self.principal_variations
+            .iter()
+            .find(|line| line.first() == Some(root_move))
+            .cloned()
+            .unwrap_or_else(|| vec![root_move.clone()])
+    }
+
+    fn new_game(&mut self) {
+        // The helpers share the main searcher's transposition table, so
+        // this clears theirs too
+        self.searcher.new_game();
+        self.principal_variations.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::game::ChessResult;
+    use crate::search::test_utils::{
+        get_mate_in_two_board, get_mate_searcher, get_material_searcher,
+    };
+    use crate::search::{stop::StopHandle, SEARCH_ABORTED};
+
+    use super::*;
+
+    #[test]
+    fn threads_find_the_same_mate() {
+        let mut searcher = RootSplitSearch::new(get_mate_searcher());
+        searcher.set_threads(4);
+        searcher.set_depth(4);
+
+        let board = get_mate_in_two_board();
+        let (best_move, evaluation) = searcher.search(&board).unwrap();
+        assert_eq!(
+            evaluation,
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3)
+        );
+
+        let line = searcher.get_principal_variation(&best_move);
+        assert_eq!(line.first(), Some(&best_move));
+    }
+
+    #[test]
+    fn evaluates_every_root_move() {
+        let board = Board::from_art(
+            "....k...\n\
+             ........\n\
+             ...p....\n\
+             ....p...\n\
+             ........\n\
+             ........\n\
+             ....Q...\n\
+             ....K...\n",
+        )
+        .unwrap();
+
+        let mut plain = get_material_searcher();
+        plain.set_depth(3);
+        let plain_order = plain.search_order(&board).unwrap();
+
+        let mut searcher = RootSplitSearch::new(get_material_searcher());
+        searcher.set_threads(3);
+        searcher.set_depth(3);
+        let order = searcher.search_order(&board).unwrap();
+
+        assert_eq!(order.len(), plain_order.len());
+        for (m, _) in &plain_order {
+            assert!(order.iter().any(|(other, _)| other == m));
+        }
+        assert_eq!(order[0].1, plain_order[0].1);
+
+        // Every other move is at best as good as the best one
+        for (_, evaluation) in &order[1..] {
+            assert!(*evaluation <= order[0].1);
+        }
+    }
+
+    #[test]
+    fn single_thread_searches_every_move_in_order() {
+        let mut searcher = RootSplitSearch::new(get_material_searcher());
+        searcher.set_threads(1);
+        searcher.set_depth(3);
+
+        let board = Board::default();
+        let moves = board.generate_moves(true).unwrap();
+        searcher.set_root_move_order(moves.iter().rev().cloned().collect());
+
+        // Every move is as good as any other at this depth, so they stay in
+        // the order they were searched in
+        let order = searcher.search_order(&board).unwrap();
+        let order_moves = order.into_iter().map(|(m, _)| m).collect::<Vec<_>>();
+        assert_eq!(order_moves, moves.into_iter().rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn helpers_count_towards_the_stats() {
+        let mut searcher = RootSplitSearch::new(get_material_searcher());
+        searcher.set_threads(2).set_depth(3);
+        searcher.search(&Board::default()).unwrap();
+
+        let stats = searcher.search_stats();
+        assert_eq!(
+            stats.nodes,
+            searcher.searcher_mut().search_stats().nodes
+                + searcher.helper_stats.nodes
+        );
+    }
+
+    #[test]
+    fn keeps_the_depth_of_the_searcher() {
+        let mut searcher = get_material_searcher();
+        searcher.set_depth(2);
+
+        let mut searcher = RootSplitSearch::new(searcher);
+        assert_eq!(searcher.searcher_mut().depth(), 2);
+    }
+
+    #[test]
+    fn gives_up_when_stopped() {
+        let mut searcher = RootSplitSearch::new(get_material_searcher());
+        searcher.set_threads(4);
+
+        let stop = StopHandle::new();
+        stop.stop();
+        searcher.set_limits(&SearchLimits {
+            stop: Some(stop),
+            ..SearchLimits::depth(4)
+        });
+
+        assert_eq!(searcher.search(&Board::default()), Err(SEARCH_ABORTED));
+    }
+}
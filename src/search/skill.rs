@@ -0,0 +1,589 @@
+use std::ops::ControlFlow;
+
+use crate::{
+    board::{chess_move::ChessMove, piece::Side, Board},
+    evaluation::evaluation_result::{
+        Centipawns, Depth, Evaluation, RelativeEvaluation,
+    },
+};
+
+use super::{
+    iterative_deepening::deepen, limits::SearchLimits,
+    observer::SearchObserver, rng::Rng, stats::SearchStats, Searcher,
+};
+
+/// The highest skill level, which plays at full strength
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// The rough Elo rating skill level 0 plays at, see `level_elo`
+const LOWEST_LEVEL_ELO: u32 = 800;
+
+/// How many Elo points every skill level is meant to be stronger than the one
+/// below it
+const ELO_PER_LEVEL: u32 = 80;
+
+/// How many squares away, counted like king moves, a capture has to be made
+/// from to be one that can be overlooked
+const LONG_CAPTURE_DISTANCE: u8 = 3;
+
+/// How many of the best root moves are evaluated exactly below full
+/// strength, and so can be played. Evaluating every root move exactly would
+/// make weak levels search far more than their depth suggests.
+const HANDICAP_LINES: usize = 6;
+
+/// Seed for picking which move to play, unless set otherwise
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// The rough Elo rating `level` is meant to play at, from 800 at level 0 up to
+/// 2400 at `MAX_SKILL_LEVEL`. These are targets, to calibrate the levels
+/// against by playing them against each other and against opponents of known
+/// strength, rather than measured ratings.
+pub fn level_elo(level: u8) -> u32 {
+    LOWEST_LEVEL_ELO + ELO_PER_LEVEL * u32::from(level.min(MAX_SKILL_LEVEL))
+}
+
+/// The highest skill level meant to play at `elo` or below, the opposite of
+/// `level_elo`. Ratings below level 0's get level 0.
+pub fn elo_level(elo: u32) -> u8 {
+    let level = elo.saturating_sub(LOWEST_LEVEL_ELO) / ELO_PER_LEVEL;
+    level.min(u32::from(MAX_SKILL_LEVEL)) as u8
+}
+
+/// How much a `SkillSearch` holds back, see `SkillLevel::new`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkillLevel {
+    /// How many plies ahead to look at most
+    pub max_depth: Depth,
+
+    /// How many nodes to look at most, or `None` for no limit
+    pub max_nodes: Option<u64>,
+
+    /// How much worse than the best move, in centipawns, a move can look and
+    /// still be played
+    pub randomness: Centipawns,
+
+    /// The chance, between 0 and 1, of overlooking a capture made from far
+    /// away, like people tend to
+    pub missed_long_captures: f64,
+
+    /// How much better than they are, in centipawns, moves towards the
+    /// opponent look, since people tend to prefer them
+    pub forward_bias: Centipawns,
+}
+
+impl SkillLevel {
+    /// Settings that don't hold back at all
+    pub const FULL_STRENGTH: Self = SkillLevel {
+        max_depth: Depth::MAX,
+        max_nodes: None,
+        randomness: 0,
+        missed_long_captures: 0.0,
+        forward_bias: 0,
+    };
+
+    /// The settings for `level`, from 0, the weakest, up to
+    /// `MAX_SKILL_LEVEL`, which is full strength. Every level below that
+    /// looks less far ahead, plays more moves that aren't the best one, and
+    /// overlooks more, than the one above it.
+    pub fn new(level: u8) -> Self {
+        if level >= MAX_SKILL_LEVEL {
+            return SkillLevel::FULL_STRENGTH;
+        }
+
+        let handicap = MAX_SKILL_LEVEL - level;
+        SkillLevel {
+            max_depth: 1 + level / 4,
+            max_nodes: Some(1000 << (level / 2)),
+            randomness: 10 * Centipawns::from(handicap),
+            missed_long_captures: f64::from(handicap) / 40.0,
+            forward_bias: 3 * Centipawns::from(handicap),
+        }
+    }
+
+    /// The settings for the highest level meant to play at `elo` or below,
+    /// see `level_elo`
+    pub fn from_elo(elo: u32) -> Self {
+        SkillLevel::new(elo_level(elo))
+    }
+}
+
+/// Wraps a searcher to play weaker on purpose, for opponents people can
+/// actually beat
+///
+/// The wrapped searcher looks no further ahead than the skill level allows,
+/// and ranks the moves as usual. The move to play is then picked the way a
+/// weaker player might: some captures made from far away are overlooked,
+/// moves towards the opponent look better than they are, and out of the
+/// moves that look close enough to the best one, each is as likely to be
+/// played. The evaluations are still the searcher's own.
+///
+/// Below full strength, the searcher is told to evaluate the best few root
+/// moves exactly, see `Searcher::set_multi_pv`, so that they can be compared.
+/// Only those moves can be played.
+///
+/// The choices are random, but seeded, see `set_seed`, so games can be
+/// played again exactly.
+pub struct SkillSearch<S> {
+    searcher: S,
+    skill: SkillLevel,
+
+    // The limits set on this searcher, which the skill level can tighten
+    limits: SearchLimits,
+
+    // How many root moves to evaluate exactly at full strength
+    multi_pv: usize,
+
+    seed: u64,
+    rng: Rng,
+}
+
+impl<S> SkillSearch<S>
+where
+    S: Searcher,
+{
+    /// Wraps `searcher`, at full strength until the skill level is set
+    pub fn new(searcher: S) -> Self {
+        SkillSearch {
+            searcher,
+            skill: SkillLevel::FULL_STRENGTH,
+            limits: SearchLimits::depth(4),
+            multi_pv: 1,
+            seed: DEFAULT_SEED,
+            rng: Rng::new(DEFAULT_SEED),
+        }
+    }
+
+    /// Sets how much to hold back, see `SkillLevel`. This is what the UCI
+    /// `Skill Level` and `UCI_Elo` options control.
+    pub fn set_skill_level(&mut self, skill: SkillLevel) -> &mut Self {
+        self.skill = skill;
+        self
+    }
+
+    /// Sets the seed moves are picked with. Every game starts from it, see
+    /// `Searcher::new_game`, so playing the same moves against this searcher
+    /// again gets the same replies.
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// The wrapped searcher, for example to change its settings. MultiPV is
+    /// set through this searcher instead, since the skill level needs the
+    /// best root moves evaluated exactly.
+    pub fn searcher_mut(&mut self) -> &mut S {
+        &mut self.searcher
+    }
+
+    // Reorders `order`, as ranked by the searcher, so that the move this skill
+    // level plays comes first
+    fn handicap(
+        &mut self,
+        board: &Board,
+        mut order: Vec<(ChessMove, Evaluation)>,
+    ) -> Vec<(ChessMove, Evaluation)> {
+        if self.skill == SkillLevel::FULL_STRENGTH {
+            return order;
+        }
+
+        // How good every move with an exact evaluation looks, or `None` if
+        // it's overlooked
+        let side = board.get_current_side();
+        let mut judged: Vec<Option<RelativeEvaluation>> = vec![];
+        for (m, evaluation) in order.iter().take(HANDICAP_LINES) {
+            let overlooked = is_long_capture(board, m)
+                && self.rng.chance(self.skill.missed_long_captures);
+            let evaluation = evaluation.relative_to(side);
+            judged.push(if overlooked {
+                None
+            } else if is_forward(m, side) {
+                Some(evaluation + self.skill.forward_bias)
+            } else {
+                Some(evaluation)
+            });
+        }
+
+        // Overlooking every move isn't an option, so then the searcher's
+        // choice stands
+        let good_enough = match judged.iter().flatten().max() {
+            Some(best) => *best - self.skill.randomness,
+            None => return order,
+        };
+        let candidates = (0..judged.len())
+            .filter(|&i| judged[i].is_some_and(|e| e >= good_enough))
+            .collect::<Vec<_>>();
+
+        let picked = candidates[self.rng.below(candidates.len())];
+        let picked_move = order.remove(picked);
+        order.insert(0, picked_move);
+        order
+    }
+
+    // Deepens with the wrapped searcher, within both `limits` and the skill
+    // level's, and handicaps what the last completed iteration found. The
+    // skill level's node limit only ends the deepening, so there's always a
+    // move to play.
+    fn search_within(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
+        let limits = SearchLimits {
+            depth: Some(limits.max_depth().min(self.skill.max_depth)),
+            nodes: tighter(limits.nodes, self.skill.max_nodes),
+            infinite: false,
+            ..limits.clone()
+        };
+        let lines = if self.skill == SkillLevel::FULL_STRENGTH {
+            self.multi_pv
+        } else {
+            HANDICAP_LINES
+        };
+        self.searcher.set_multi_pv(lines);
+
+        let order = deepen(&mut self.searcher, board, &limits, |_| {
+            ControlFlow::Continue(())
+        })?;
+        Ok(self.handicap(board, order))
+    }
+}
+
+// The tighter of two node limits, either of which can be missing
+fn tighter(limit: Option<u64>, max: Option<u64>) -> Option<u64> {
+    match (limit, max) {
+        (Some(limit), Some(max)) => Some(limit.min(max)),
+        (limit, max) => limit.or(max),
+    }
+}
+
+// Whether `chess_move` captures something from `LONG_CAPTURE_DISTANCE` or
+// more squares away
+fn is_long_capture(board: &Board, chess_move: &ChessMove) -> bool {
+    match chess_move {
+        ChessMove::SimpleMove(from, to) => {
+            let distance =
+                from.file.abs_diff(to.file).max(from.rank.abs_diff(to.rank));
+            distance >= LONG_CAPTURE_DISTANCE
+                && board.get_captured_piece(chess_move).is_some()
+        }
+        _ => false,
+    }
+}
+
+// Whether `chess_move` moves a piece towards `side`'s opponent
+fn is_forward(chess_move: &ChessMove, side: Side) -> bool {
+    match chess_move {
+        ChessMove::SimpleMove(from, to) | ChessMove::EnPassant(from, to, _) => {
+            match side {
+                Side::White => to.rank > from.rank,
+                Side::Black => to.rank < from.rank,
+            }
+        }
+        ChessMove::Castling(_) | ChessMove::NullMove => false,
+    }
+}
+
+impl<S> Searcher for SkillSearch<S>
+where
+    S: Searcher,
+{
+    /// Searches iteratively deeper, as deep as the set limits and the skill
+    /// level both allow, see `deepen`. Running out of the skill level's
+    /// nodes doesn't make the search give up, the last completed iteration
+    /// is used instead.
+    fn search_order(
+        &mut self,
+        board: &Board,
+    ) -> Result<Vec<(ChessMove, Evaluation)>, &'static str> {
+        let limits = self.limits.clone();
+        self.search_within(board, &limits)
+    }
+
+    fn set_limits(&mut self, limits: &SearchLimits) {
+        self.limits = limits.clone();
+    }
+
+    fn set_root_move_order(&mut self, moves: Vec<ChessMove>) {
+        self.searcher.set_root_move_order(moves);
+    }
+
+    /// Only applies at full strength, below which the best few root moves
+    /// are evaluated exactly
+    fn set_multi_pv(&mut self, lines: usize) {
+        self.multi_pv = lines;
+    }
+
+    fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.searcher.set_observer(observer);
+    }
+
+    fn observer(&mut self) -> Option<&mut dyn SearchObserver> {
+        self.searcher.observer()
+    }
+
+    fn search_stats(&self) -> SearchStats {
+        self.searcher.search_stats()
+    }
+
+    fn get_principal_variation(&self, root_move: &ChessMove) -> Vec<ChessMove> {
+        self.searcher.get_principal_variation(root_move)
+    }
+
+    fn new_game(&mut self) {
+        self.searcher.new_game();
+        self.rng = Rng::new(self.seed);
+    }
+
+    /// Deepens with the wrapped searcher, within both `limits` and the skill
+    /// level's, and only picks a move once, from the last completed
+    /// iteration
+    fn search_with_limits(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+    ) -> Result<(ChessMove, Evaluation), &'static str> {
+        self.search_within(board, limits)?
+            .first()
+            .cloned()
+            .ok_or("No moves possible")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use crate::board::game::ChessResult;
+    use crate::evaluation::material::MaterialEvaluator;
+    use crate::search::alphabeta::AlphabetaSearch;
+    use crate::search::test_utils::{
+        get_mate_in_two_board, get_mate_searcher, get_material_searcher,
+    };
+
+    use super::*;
+
+    fn get_searcher(
+    ) -> SkillSearch<AlphabetaSearch<MaterialEvaluator, MaterialEvaluator>>
+    {
+        SkillSearch::new(get_material_searcher())
+    }
+
+    // `skill`, but only looking two plies ahead, which is plenty for the
+    // positions here
+    fn shallow(skill: SkillLevel) -> SkillLevel {
+        SkillLevel {
+            max_depth: 2,
+            ..skill
+        }
+    }
+
+    #[test]
+    fn full_strength_plays_like_the_searcher() {
+        let board = Board::default();
+        let mut searcher = get_searcher();
+        searcher.set_multi_pv(usize::MAX);
+        searcher.set_depth(3);
+
+        let mut wrapped = get_material_searcher();
+        wrapped.set_multi_pv(usize::MAX);
+        wrapped.set_depth(3);
+
+        assert_eq!(
+            searcher.search_order(&board).unwrap(),
+            wrapped.search_order(&board).unwrap()
+        );
+    }
+
+    #[test]
+    fn low_levels_look_less_far_ahead() {
+        let board = get_mate_in_two_board();
+        let mate = Evaluation::Certain(ChessResult::Checkmate(Side::Black), 3);
+
+        let mut searcher = SkillSearch::new(get_mate_searcher());
+        searcher.set_depth(4);
+        assert_eq!(searcher.search(&board).unwrap().1, mate);
+
+        searcher.set_skill_level(SkillLevel::new(0));
+        assert_ne!(searcher.search(&board).unwrap().1, mate);
+    }
+
+    #[test]
+    fn picks_among_near_best_moves() {
+        let board = Board::default();
+        let mut searcher = get_searcher();
+        searcher.set_skill_level(SkillLevel::new(0)).set_depth(1);
+
+        let mut picked = vec![];
+        for seed in 1..10 {
+            let (chess_move, evaluation) =
+                searcher.set_seed(seed).search(&board).unwrap();
+            assert!(evaluation >= Evaluation::Estimate(-200));
+            if !picked.contains(&chess_move) {
+                picked.push(chess_move);
+            }
+        }
+        assert!(picked.len() > 1);
+    }
+
+    #[test]
+    fn only_plays_moves_evaluated_exactly() {
+        let board = Board::default();
+        let mut searcher = get_searcher();
+        searcher
+            .set_skill_level(SkillLevel {
+                randomness: Centipawns::MAX / 2,
+                ..shallow(SkillLevel::new(0))
+            })
+            .set_depth(1);
+
+        let mut wrapped = get_material_searcher();
+        wrapped.set_multi_pv(HANDICAP_LINES);
+        wrapped.set_depth(1);
+        let best_moves = wrapped.search_order(&board).unwrap();
+
+        for seed in 1..20 {
+            let (chess_move, _) =
+                searcher.set_seed(seed).search(&board).unwrap();
+            assert!(best_moves[..HANDICAP_LINES]
+                .iter()
+                .any(|(m, _)| *m == chess_move));
+        }
+    }
+
+    #[test]
+    fn overlooks_long_captures() {
+        // The queen is there for the taking, from the other end of the board
+        let board = Board::from_art(
+            "q......k\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             .......P\n\
+             R......K\n",
+        )
+        .unwrap();
+        let capture: ChessMove = "a1a8".try_into().unwrap();
+
+        let mut searcher = get_searcher();
+        let skill = shallow(SkillLevel::FULL_STRENGTH);
+        searcher.set_skill_level(skill);
+        assert_eq!(searcher.search(&board).unwrap().0, capture);
+
+        searcher.set_skill_level(SkillLevel {
+            missed_long_captures: 1.0,
+            ..skill
+        });
+        assert_ne!(searcher.search(&board).unwrap().0, capture);
+    }
+
+    #[test]
+    fn compares_exact_evaluations() {
+        let board = Board::from_art(
+            "q......k\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             .......P\n\
+             R......K\n",
+        )
+        .unwrap();
+
+        let mut searcher = get_searcher();
+        searcher.set_skill_level(shallow(SkillLevel::FULL_STRENGTH));
+
+        let mut wrapped = get_material_searcher();
+        wrapped.set_multi_pv(HANDICAP_LINES);
+        wrapped.set_depth(2);
+
+        let evaluations = |order: Vec<(ChessMove, Evaluation)>| {
+            order
+                .into_iter()
+                .take(HANDICAP_LINES)
+                .map(|(_, e)| e)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            evaluations(searcher.search_order(&board).unwrap()),
+            evaluations(wrapped.search_order(&board).unwrap())
+        );
+    }
+
+    #[test]
+    fn prefers_forward_moves() {
+        let board = Board::from_art(
+            ".......k\n\
+             .......p\n\
+             ........\n\
+             ........\n\
+             ...K....\n\
+             ........\n\
+             P.......\n\
+             ........\n",
+        )
+        .unwrap();
+
+        let mut searcher = get_searcher();
+        searcher.set_skill_level(SkillLevel {
+            forward_bias: 50,
+            ..shallow(SkillLevel::FULL_STRENGTH)
+        });
+
+        for seed in 1..10 {
+            let (chess_move, _) =
+                searcher.set_seed(seed).search(&board).unwrap();
+            assert!(is_forward(&chess_move, Side::White));
+        }
+    }
+
+    #[test]
+    fn keeps_to_the_node_limit_of_the_level() {
+        let mut searcher = get_searcher();
+        searcher.set_skill_level(SkillLevel::new(0));
+
+        let board = Board::default();
+        let (chess_move, _) = searcher
+            .search_with_limits(&board, &SearchLimits::infinite())
+            .unwrap();
+        assert!(board.generate_moves(true).unwrap().contains(&chess_move));
+    }
+
+    #[test]
+    fn every_level_finds_a_move() {
+        // After 1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5
+        let board = Board::from_art(
+            "r.bqk.nr\n\
+             pppp.ppp\n\
+             ..n.....\n\
+             ..b.p...\n\
+             ..B.P...\n\
+             .....N..\n\
+             PPPP.PPP\n\
+             RNBQK..R\n",
+        )
+        .unwrap();
+
+        let mut searcher = get_searcher();
+        for level in 0..=MAX_SKILL_LEVEL {
+            searcher.set_skill_level(SkillLevel::new(level));
+            assert!(searcher.search(&board).is_ok(), "level {}", level);
+        }
+    }
+
+    #[test]
+    fn levels_map_to_elo() {
+        assert_eq!(level_elo(0), 800);
+        assert_eq!(level_elo(MAX_SKILL_LEVEL), 2400);
+        for level in 0..=MAX_SKILL_LEVEL {
+            assert_eq!(elo_level(level_elo(level)), level);
+            assert_eq!(elo_level(level_elo(level) + 1), level);
+        }
+
+        assert_eq!(SkillLevel::from_elo(0), SkillLevel::new(0));
+        assert_eq!(SkillLevel::from_elo(3000), SkillLevel::FULL_STRENGTH);
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+/// Counters describing what a search did, for comparing how different
+/// settings affect the search
+///
+/// Searchers only fill in the counters that make sense for them, the rest
+/// stay at zero.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Every position looked at, including the ones in quiescence search
+    pub nodes: u64,
+
+    /// Positions looked at by quiescence search
+    pub quiescence_nodes: u64,
+
+    /// Positions that were found in the transposition table
+    pub tt_hits: u64,
+
+    /// Positions whose search was skipped because the transposition table
+    /// already had a good enough result for them
+    pub tt_cutoffs: u64,
+
+    /// Positions whose search stopped early because a move was found that was
+    /// too good for the opponent to allow
+    pub beta_cutoffs: u64,
+
+    /// Beta cutoffs that happened on the first move searched. The closer this
+    /// is to `beta_cutoffs`, the better the move ordering.
+    pub first_move_beta_cutoffs: u64,
+
+    /// Positions whose search was skipped because passing the turn was
+    /// already too good for the opponent to allow
+    pub null_move_cutoffs: u64,
+
+    /// Positions whose search was skipped because their static evaluation
+    /// was already too good for the opponent to allow, by a wide margin
+    pub reverse_futility_cutoffs: u64,
+
+    /// Quiet moves near the leaves that weren't searched, because even
+    /// with a wide margin they couldn't have improved the evaluation
+    pub futility_pruned_moves: u64,
+
+    /// Late quiet moves that were first searched less deeply
+    pub reduced_moves: u64,
+
+    /// Reduced moves that turned out better than expected, and had to be
+    /// searched again to full depth
+    pub reduction_re_searches: u64,
+
+    /// Forcing moves that were searched a ply deeper than the others
+    pub extensions: u64,
+
+    /// How many positions were looked at by the main search at every ply,
+    /// counting from the root. Doesn't include quiescence search.
+    pub nodes_per_ply: Vec<u64>,
+
+    /// How long the search took
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    /// Counts a position looked at by the main search, `ply` plies from the
+    /// root
+    pub fn count_node(&mut self, ply: usize) {
+        self.nodes += 1;
+
+        if self.nodes_per_ply.len() <= ply {
+            self.nodes_per_ply.resize(ply + 1, 0);
+        }
+        self.nodes_per_ply[ply] += 1;
+    }
+
+    /// Adds the counters from `other` to these, for example to sum up the
+    /// iterations of an iterative search
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.quiescence_nodes += other.quiescence_nodes;
+        self.tt_hits += other.tt_hits;
+        self.tt_cutoffs += other.tt_cutoffs;
+        self.beta_cutoffs += other.beta_cutoffs;
+        self.first_move_beta_cutoffs += other.first_move_beta_cutoffs;
+        self.null_move_cutoffs += other.null_move_cutoffs;
+        self.reverse_futility_cutoffs += other.reverse_futility_cutoffs;
+        self.futility_pruned_moves += other.futility_pruned_moves;
+        self.reduced_moves += other.reduced_moves;
+        self.reduction_re_searches += other.reduction_re_searches;
+        self.extensions += other.extensions;
+        self.elapsed += other.elapsed;
+
+        if self.nodes_per_ply.len() < other.nodes_per_ply.len() {
+            self.nodes_per_ply.resize(other.nodes_per_ply.len(), 0);
+        }
+        for (total, nodes) in
+            self.nodes_per_ply.iter_mut().zip(&other.nodes_per_ply)
+        {
+            *total += nodes;
+        }
+    }
+
+    /// The share of beta cutoffs that happened on the first move, between 0
+    /// and 1. Returns `None` if there weren't any cutoffs.
+    pub fn first_move_cutoff_rate(&self) -> Option<f64> {
+        (self.beta_cutoffs > 0).then(|| {
+            self.first_move_beta_cutoffs as f64 / self.beta_cutoffs as f64
+        })
+    }
+
+    /// How many times more positions were looked at on each ply than on the
+    /// one before it. The first element is for ply 1.
+    pub fn branching_factors(&self) -> Vec<f64> {
+        self.nodes_per_ply
+            .windows(2)
+            .map(|pair| pair[1] as f64 / pair[0] as f64)
+            .collect()
+    }
+
+    /// How many positions were looked at per second
+    pub fn nodes_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.nodes as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_nodes_per_ply() {
+        let mut stats = SearchStats::default();
+        stats.count_node(0);
+        stats.count_node(1);
+        stats.count_node(1);
+        stats.count_node(2);
+        stats.count_node(2);
+        stats.count_node(2);
+        stats.count_node(2);
+
+        assert_eq!(stats.nodes, 7);
+        assert_eq!(stats.nodes_per_ply, vec![1, 2, 4]);
+        assert_eq!(stats.branching_factors(), vec![2.0, 2.0]);
+    }
+
+    #[test]
+    fn merges_counters() {
+        let mut first = SearchStats::default();
+        first.count_node(0);
+        first.beta_cutoffs = 2;
+        first.first_move_beta_cutoffs = 1;
+        first.elapsed = Duration::from_millis(500);
+
+        let mut second = SearchStats::default();
+        second.count_node(0);
+        second.count_node(1);
+        second.beta_cutoffs = 2;
+        second.first_move_beta_cutoffs = 2;
+        second.elapsed = Duration::from_millis(500);
+
+        first.merge(&second);
+
+        assert_eq!(first.nodes, 3);
+        assert_eq!(first.nodes_per_ply, vec![2, 1]);
+        assert_eq!(first.first_move_cutoff_rate(), Some(0.75));
+        assert_eq!(first.nodes_per_second(), 3.0);
+    }
+
+    #[test]
+    fn rates_are_safe_without_data() {
+        let stats = SearchStats::default();
+
+        assert_eq!(stats.first_move_cutoff_rate(), None);
+        assert!(stats.branching_factors().is_empty());
+        assert_eq!(stats.nodes_per_second(), 0.0);
+    }
+}
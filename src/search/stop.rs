@@ -0,0 +1,82 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A flag that tells searches to stop as soon as possible
+///
+/// Handles are cheap to clone, and every clone controls the same flag, so one
+/// can be given to a searcher and another kept by whoever decides when the
+/// search should end, for example a thread reading commands from a GUI.
+///
+/// Once stopped, a handle stays stopped until it's reset.
+#[derive(Clone, Debug, Default)]
+pub struct StopHandle {
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tells every search using this handle to stop
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether `stop` has been called since the handle was created or last
+    /// reset
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// Lets searches using this handle run again
+    pub fn reset(&self) {
+        self.stopped.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Two handles are equal if they control the same flag
+impl PartialEq for StopHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.stopped, &other.stopped)
+    }
+}
+
+impl Eq for StopHandle {}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn clones_share_the_flag() {
+        let handle = StopHandle::new();
+        let clone = handle.clone();
+        assert!(!clone.is_stopped());
+
+        handle.stop();
+        assert!(clone.is_stopped());
+
+        clone.reset();
+        assert!(!handle.is_stopped());
+    }
+
+    #[test]
+    fn can_stop_from_another_thread() {
+        let handle = StopHandle::new();
+        let clone = handle.clone();
+
+        thread::spawn(move || clone.stop()).join().unwrap();
+
+        assert!(handle.is_stopped());
+    }
+
+    #[test]
+    fn separate_handles_are_different() {
+        assert_ne!(StopHandle::new(), StopHandle::new());
+    }
+}
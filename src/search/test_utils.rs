@@ -0,0 +1,95 @@
+use crate::{
+    board::Board,
+    evaluation::{material::MaterialEvaluator, result::ResultEvaluator},
+};
+
+use super::alphabeta::AlphabetaSearch;
+
+// White mates in two
+pub fn get_mate_in_two_board() -> Board {
+    Board::from_art(
+        ".k......\n\
+         ........\n\
+         ..K.....\n\
+         ..R.....\n\
+         ........\n\
+         ........\n\
+         ........\n\
+         ........\n",
+    )
+    .unwrap()
+}
+
+// White mates in four
+pub fn get_mate_in_four_board() -> Board {
+    Board::from_art(
+        "..N.....\n\
+         ........\n\
+         k..K....\n\
+         ........\n\
+         p.PB....\n\
+         P.......\n\
+         ........\n\
+         ........\n",
+    )
+    .unwrap()
+}
+
+// White's queen against a lone black pawn, a quick position to search a few
+// plies deep
+pub fn get_queen_against_pawn_board() -> Board {
+    Board::from_art(
+        "....k...\n\
+         ...p....\n\
+         ........\n\
+         ........\n\
+         ........\n\
+         ........\n\
+         ...Q....\n\
+         ....K...\n",
+    )
+    .unwrap()
+}
+
+// A middlegame where White is a rook up
+pub fn get_rook_up_board() -> Board {
+    Board::from_art(
+        "....k...\n\
+         ppp..ppp\n\
+         ..n.....\n\
+         ...p....\n\
+         ...P.B..\n\
+         ..N.....\n\
+         PPP..PPP\n\
+         R...K...\n",
+    )
+    .unwrap()
+}
+
+// A position with pieces hanging on both sides, where searches have plenty
+// of captures to look at
+pub fn get_tactical_board() -> Board {
+    Board::from_art(
+        "r...k...\n\
+         ..p.....\n\
+         ....n...\n\
+         ...p....\n\
+         .B.Q....\n\
+         ........\n\
+         .....PPP\n\
+         ......K.\n",
+    )
+    .unwrap()
+}
+
+// A searcher that only counts material
+pub fn get_material_searcher(
+) -> AlphabetaSearch<MaterialEvaluator, MaterialEvaluator> {
+    AlphabetaSearch::new(MaterialEvaluator::new(), MaterialEvaluator::new())
+}
+
+// A searcher that counts material, and recognises mates at the end of lines
+pub fn get_mate_searcher() -> AlphabetaSearch<MaterialEvaluator, ResultEvaluator>
+{
+    AlphabetaSearch::new(MaterialEvaluator::new(), ResultEvaluator::new())
+}
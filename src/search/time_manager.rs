@@ -0,0 +1,276 @@
+use std::time::{Duration, Instant};
+
+use crate::board::Board;
+
+/// The state of the engine's clock at the start of a move, as reported by
+/// whoever is running the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    /// Time left on the engine's clock
+    pub remaining: Duration,
+
+    /// Time added to the clock after every move
+    pub increment: Duration,
+
+    /// Number of moves left until the next time control. `None` if the rest of
+    /// the game has to be played with the remaining time (sudden death)
+    pub moves_to_go: Option<u32>,
+}
+
+/// Points in time that a timed search has to respect
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Deadlines {
+    /// Once this has passed, a search shouldn't start another iteration. It
+    /// may still finish the iteration it's currently in.
+    pub soft: Instant,
+
+    /// Once this has passed, a search should stop as soon as possible
+    pub hard: Instant,
+}
+
+/// A time manager figures out how much of the clock to spend on a move
+pub struct TimeManager {
+    /// Time kept in reserve for every move, to account for communication lag
+    /// between the engine and whoever is running the game
+    move_overhead: Duration,
+
+    /// How many moves we assume are left in the game when playing sudden
+    /// death
+    expected_moves_to_go: u32,
+
+    /// How much longer than the soft limit the hard limit is
+    hard_limit_ratio: f64,
+}
+
+/// The soft limit never uses more than this fraction of the available time, so
+/// that an iteration overrunning it still has room to finish
+const MAX_SOFT_FRACTION: f64 = 0.5;
+
+/// The hard limit never uses more than this fraction of the available time, so
+/// there's always time left for the rest of the game
+const MAX_HARD_FRACTION: f64 = 0.8;
+
+/// Number of legal moves in a "typical" middlegame position
+const TYPICAL_MOVE_COUNT: f64 = 30.0;
+
+impl TimeManager {
+    pub fn new() -> Self {
+        TimeManager {
+            move_overhead: Duration::from_millis(50),
+            expected_moves_to_go: 30,
+            hard_limit_ratio: 3.0,
+        }
+    }
+
+    pub fn set_move_overhead(&mut self, overhead: Duration) -> &mut Self {
+        self.move_overhead = overhead;
+        self
+    }
+
+    pub fn set_expected_moves_to_go(&mut self, moves: u32) -> &mut Self {
+        self.expected_moves_to_go = moves.max(1);
+        self
+    }
+
+    pub fn set_hard_limit_ratio(&mut self, ratio: f64) -> &mut Self {
+        self.hard_limit_ratio = ratio.max(1.0);
+        self
+    }
+
+    /// Allocates deadlines for a search starting at `start`
+    ///
+    /// `complexity` scales the time spent, with 1.0 being a typical position.
+    /// See `estimate_complexity` for a way to get this from a board.
+    pub fn allocate(
+        &self,
+        start: Instant,
+        control: &TimeControl,
+        complexity: f64,
+    ) -> Deadlines {
+        let available = control.remaining.saturating_sub(self.move_overhead);
+        let moves_to_go = control
+            .moves_to_go
+            .unwrap_or(self.expected_moves_to_go)
+            .max(1);
+
+        // We get the increment back after the move, so we can spend it, but
+        // only if we actually have it on the clock
+        let base = available / moves_to_go + control.increment.min(available);
+
+        let soft =
+            scale(base, complexity, available.mul_f64(MAX_SOFT_FRACTION));
+        let hard = scale(
+            soft,
+            self.hard_limit_ratio,
+            available.mul_f64(MAX_HARD_FRACTION),
+        );
+
+        Deadlines {
+            soft: start + soft,
+            hard: start + hard,
+        }
+    }
+}
+
+impl Default for TimeManager {
+    fn default() -> Self {
+        TimeManager::new()
+    }
+}
+
+// Multiplies `duration` by `factor`, but never past `max`. Factors too big to
+// give a duration, like infinity, give `max`, and negative ones or NaN give
+// nothing.
+fn scale(duration: Duration, factor: f64, max: Duration) -> Duration {
+    if factor.is_nan() || factor <= 0.0 {
+        return Duration::ZERO;
+    }
+
+    Duration::try_from_secs_f64(duration.as_secs_f64() * factor)
+        .map_or(max, |scaled| scaled.min(max))
+}
+
+/// Estimates how much thinking a position needs, as a factor to pass to
+/// `TimeManager::allocate`
+///
+/// Positions with more legal moves are assumed to be harder. If there's only
+/// one legal move, there's nothing to think about, so this returns 0.
+pub fn estimate_complexity(board: &Board) -> Result<f64, &'static str> {
+    let move_count = board.generate_moves(true)?.len();
+
+    if move_count <= 1 {
+        return Ok(0.0);
+    }
+
+    Ok((move_count as f64 / TYPICAL_MOVE_COUNT).clamp(0.5, 1.5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sudden_death(remaining_ms: u64, increment_ms: u64) -> TimeControl {
+        TimeControl {
+            remaining: Duration::from_millis(remaining_ms),
+            increment: Duration::from_millis(increment_ms),
+            moves_to_go: None,
+        }
+    }
+
+    #[test]
+    fn soft_deadline_is_before_hard_deadline() {
+        let start = Instant::now();
+        let deadlines =
+            TimeManager::new().allocate(start, &sudden_death(60_000, 0), 1.0);
+
+        assert!(deadlines.soft > start);
+        assert!(deadlines.soft < deadlines.hard);
+    }
+
+    #[test]
+    fn spends_fraction_of_clock_in_sudden_death() {
+        let start = Instant::now();
+        let mut manager = TimeManager::new();
+        manager
+            .set_move_overhead(Duration::ZERO)
+            .set_expected_moves_to_go(30);
+
+        let deadlines = manager.allocate(start, &sudden_death(60_000, 0), 1.0);
+
+        assert_eq!(deadlines.soft - start, Duration::from_secs(2));
+        assert_eq!(deadlines.hard - start, Duration::from_secs(6));
+    }
+
+    #[test]
+    fn spends_increment() {
+        let start = Instant::now();
+        let mut manager = TimeManager::new();
+        manager.set_move_overhead(Duration::ZERO);
+
+        let without_increment =
+            manager.allocate(start, &sudden_death(60_000, 0), 1.0);
+        let with_increment =
+            manager.allocate(start, &sudden_death(60_000, 1_000), 1.0);
+
+        assert_eq!(
+            with_increment.soft - without_increment.soft,
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn uses_moves_to_go() {
+        let start = Instant::now();
+        let mut manager = TimeManager::new();
+        manager.set_move_overhead(Duration::ZERO);
+
+        let control = TimeControl {
+            remaining: Duration::from_secs(60),
+            increment: Duration::ZERO,
+            moves_to_go: Some(60),
+        };
+        let deadlines = manager.allocate(start, &control, 1.0);
+
+        assert_eq!(deadlines.soft - start, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn never_overspends_on_last_move() {
+        let start = Instant::now();
+        let control = TimeControl {
+            remaining: Duration::from_secs(10),
+            increment: Duration::ZERO,
+            moves_to_go: Some(1),
+        };
+        let deadlines = TimeManager::new().allocate(start, &control, 1.5);
+
+        assert!(deadlines.hard - start < control.remaining);
+    }
+
+    #[test]
+    fn complex_positions_get_more_time() {
+        let start = Instant::now();
+        let manager = TimeManager::new();
+        let control = sudden_death(60_000, 0);
+
+        let simple = manager.allocate(start, &control, 0.5);
+        let complex = manager.allocate(start, &control, 1.5);
+
+        assert!(complex.soft > simple.soft);
+        assert!(complex.hard > simple.hard);
+    }
+
+    #[test]
+    fn survives_unreasonable_factors() {
+        let start = Instant::now();
+        let control = sudden_death(60_000, 0);
+        let mut manager = TimeManager::new();
+        manager.set_hard_limit_ratio(f64::INFINITY);
+
+        for complexity in [f64::INFINITY, f64::MAX, f64::NAN, -1.0] {
+            let deadlines = manager.allocate(start, &control, complexity);
+            assert!(deadlines.hard - start < control.remaining);
+        }
+
+        let deadlines = manager.allocate(start, &control, f64::INFINITY);
+        assert!(deadlines.soft > start);
+    }
+
+    #[test]
+    fn only_move_has_no_complexity() {
+        let mut board = Board::from_art(
+            "k.......\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             ........\n\
+             .R.....K\n",
+        )
+        .unwrap();
+        board.flip_current_side();
+
+        assert_eq!(estimate_complexity(&board).unwrap(), 0.0);
+    }
+}
@@ -0,0 +1,513 @@
+use std::{
+    mem::size_of,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        OnceLock,
+    },
+};
+
+use crate::{
+    board::{
+        chess_move::ChessMove, game::ChessResult, piece::Side, square::Square,
+        CastlingDirection,
+    },
+    evaluation::evaluation_result::{Centipawns, Depth, Evaluation},
+};
+
+/// How a stored evaluation relates to the real evaluation of a position
+///
+/// Bounds are in the same terms as `Evaluation`'s ordering, i.e. a `Lower`
+/// bound means the position is at least this good for White.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The evaluation is exact
+    Exact,
+
+    /// The real evaluation is at least as good for White as the stored one.
+    /// Happens when a search is cut off because White had a move that was
+    /// too good.
+    Lower,
+
+    /// The real evaluation is at most as good for White as the stored one.
+    /// Happens when a search is cut off because Black had a move that was
+    /// too good.
+    Upper,
+}
+
+impl Bound {
+    /// The bound from `side`'s point of view, where `Lower` means the real
+    /// evaluation is at least as good for `side`. Goes along with
+    /// `Evaluation::relative_to`, and converts back the same way.
+    pub fn relative_to(self, side: Side) -> Bound {
+        match (self, side) {
+            (Bound::Lower, Side::Black) => Bound::Upper,
+            (Bound::Upper, Side::Black) => Bound::Lower,
+            (bound, _) => bound,
+        }
+    }
+}
+
+/// The result of searching a position, as remembered by the table
+///
+/// Evaluations are stored as-is. `Certain` results already count plies from
+/// the position they were found for rather than from the root of the search,
+/// so they stay correct when the same position is reached through a longer or
+/// shorter line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: Depth,
+    pub bound: Bound,
+    pub evaluation: Evaluation,
+    pub best_move: Option<ChessMove>,
+    generation: u8,
+}
+
+/// A fixed size cache of search results, keyed by position hash
+///
+/// When two positions map to the same slot, the entry that was searched
+/// deeper is kept, unless it's left over from an earlier search.
+///
+/// The table can be shared between threads searching at the same time, for
+/// example behind an `Arc`. It doesn't lock: every entry is packed into a
+/// single word, and stored next to its key xor'ed with that word. If two
+/// threads write the same slot at once and the words get mixed up, the key
+/// doesn't match anymore, so the entry is just missing rather than wrong.
+///
+/// The memory is only allocated once the first entry is stored, so tables
+/// that never get used are free.
+pub struct TranspositionTable {
+    slots: OnceLock<Box<[Slot]>>,
+    capacity: usize,
+    generation: AtomicU8,
+}
+
+#[derive(Default)]
+struct Slot {
+    // The key xor'ed with `data`
+    check: AtomicU64,
+
+    // An `Entry` without its key, see `pack`. Zero for empty slots.
+    data: AtomicU64,
+}
+
+impl TranspositionTable {
+    /// Creates a table that takes up roughly `megabytes` of memory
+    pub fn new(megabytes: usize) -> Self {
+        let capacity = (megabytes * 1024 * 1024 / size_of::<Slot>())
+            // We always want space for at least one entry, so indexing works
+            .max(1);
+
+        TranspositionTable {
+            slots: OnceLock::new(),
+            capacity,
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// Forget everything, for example because a new game started
+    pub fn clear(&self) {
+        for slot in self.slots.get().into_iter().flatten() {
+            slot.data.store(0, Ordering::Relaxed);
+            slot.check.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a search for a new position. Entries stored before
+    /// this are treated as stale, and are replaced more eagerly.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Looks up the entry for the position with hash `key`, if there is one
+    pub fn get(&self, key: u64) -> Option<Entry> {
+        let slot = &self.slots.get()?[self.index(key)];
+        let data = slot.data.load(Ordering::Relaxed);
+        let check = slot.check.load(Ordering::Relaxed);
+
+        if data == 0 || check ^ data != key {
+            return None;
+        }
+        unpack(key, data)
+    }
+
+    /// Stores a search result for the position with hash `key`, if the
+    /// replacement scheme allows it
+    pub fn store(
+        &self,
+        key: u64,
+        depth: Depth,
+        bound: Bound,
+        evaluation: Evaluation,
+        best_move: Option<ChessMove>,
+    ) {
+        let slots = self.slots.get_or_init(|| {
+            (0..self.capacity).map(|_| Slot::default()).collect()
+        });
+        let slot = &slots[self.index(key)];
+        let generation = self.generation.load(Ordering::Relaxed);
+
+        let existing_data = slot.data.load(Ordering::Relaxed);
+        let existing_key = slot.check.load(Ordering::Relaxed) ^ existing_data;
+        let existing = match existing_data {
+            0 => None,
+            data => unpack(existing_key, data),
+        };
+
+        let should_replace = match &existing {
+            None => true,
+            Some(existing) => {
+                existing.key == key
+                    || existing.generation != generation
+                    || depth >= existing.depth
+            }
+        };
+
+        if should_replace {
+            // If we don't have a move for this position, don't throw away one
+            // we found earlier
+            let best_move = best_move.or_else(|| {
+                existing.filter(|e| e.key == key).and_then(|e| e.best_move)
+            });
+
+            let data = pack(&Entry {
+                key,
+                depth,
+                bound,
+                evaluation,
+                best_move,
+                generation,
+            });
+            slot.data.store(data, Ordering::Relaxed);
+            slot.check.store(key ^ data, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.capacity as u64) as usize
+    }
+}
+
+// Entries are packed into a word as follows, starting from the lowest bits:
+//
+// - 19 bits for the best move, see `pack_move`
+// - 8 bits for the depth
+// - 2 bits for the bound, which is never zero, so that no entry packs to zero
+// - 8 bits for the generation
+// - 2 bits for the kind of evaluation, and 29 bits for its centipawns or the
+//   plies to the result. Centipawns are clamped to fit.
+const MOVE_BITS: u32 = 19;
+const DEPTH_SHIFT: u32 = MOVE_BITS;
+const BOUND_SHIFT: u32 = DEPTH_SHIFT + 8;
+const GENERATION_SHIFT: u32 = BOUND_SHIFT + 2;
+const EVALUATION_KIND_SHIFT: u32 = GENERATION_SHIFT + 8;
+const EVALUATION_SHIFT: u32 = EVALUATION_KIND_SHIFT + 2;
+const EVALUATION_BITS: u32 = 64 - EVALUATION_SHIFT;
+
+const MAX_STORED_CENTIPAWNS: Centipawns = (1 << (EVALUATION_BITS - 1)) - 1;
+
+fn pack(entry: &Entry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    let (kind, value) = match entry.evaluation {
+        Evaluation::Estimate(c) => (
+            0,
+            c.clamp(-MAX_STORED_CENTIPAWNS, MAX_STORED_CENTIPAWNS) as u64,
+        ),
+        Evaluation::Certain(ChessResult::Draw, plies) => (1, plies.into()),
+        Evaluation::Certain(ChessResult::Checkmate(Side::White), plies) => {
+            (2, plies.into())
+        }
+        Evaluation::Certain(ChessResult::Checkmate(Side::Black), plies) => {
+            (3, plies.into())
+        }
+    };
+
+    pack_move(entry.best_move.as_ref())
+        | u64::from(entry.depth) << DEPTH_SHIFT
+        | bound << BOUND_SHIFT
+        | u64::from(entry.generation) << GENERATION_SHIFT
+        | kind << EVALUATION_KIND_SHIFT
+        | (value & ((1 << EVALUATION_BITS) - 1)) << EVALUATION_SHIFT
+}
+
+fn unpack(key: u64, data: u64) -> Option<Entry> {
+    let bound = match (data >> BOUND_SHIFT) & 0b11 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+
+    // Shifting the top bits back down as signed sign-extends the centipawns
+    let value = (data as i64) >> EVALUATION_SHIFT;
+    let plies = value as Depth;
+    let evaluation = match (data >> EVALUATION_KIND_SHIFT) & 0b11 {
+        0 => Evaluation::Estimate(value as Centipawns),
+        1 => Evaluation::Certain(ChessResult::Draw, plies),
+        2 => Evaluation::Certain(ChessResult::Checkmate(Side::White), plies),
+        _ => Evaluation::Certain(ChessResult::Checkmate(Side::Black), plies),
+    };
+
+    Some(Entry {
+        key,
+        depth: (data >> DEPTH_SHIFT) as Depth,
+        bound,
+        evaluation,
+        best_move: unpack_move(data & ((1 << MOVE_BITS) - 1)),
+        generation: (data >> GENERATION_SHIFT) as u8,
+    })
+}
+
+// Moves take 3 bits for their kind, and 8 bits for each of the squares they
+// go from and to: 4 for the file and 4 for the rank. The square an en
+// passant capture takes on can be worked out from the other two.
+//
+// That covers boards of up to 16 by 16 squares. Moves on bigger boards are
+// stored as no move at all, so the search just doesn't get to try them
+// first, rather than getting back a move to the wrong square.
+const SQUARE_BITS: u32 = 8;
+const MAX_STORED_COORDINATE: u8 = (1 << (SQUARE_BITS / 2)) - 1;
+
+fn pack_move(chess_move: Option<&ChessMove>) -> u64 {
+    let fits = |s: &Square| {
+        s.file <= MAX_STORED_COORDINATE && s.rank <= MAX_STORED_COORDINATE
+    };
+    let square =
+        |s: &Square| u64::from(s.rank) << (SQUARE_BITS / 2) | u64::from(s.file);
+    let (kind, from, to) = match chess_move {
+        Some(
+            ChessMove::SimpleMove(from, to) | ChessMove::EnPassant(from, to, _),
+        ) if !fits(from) || !fits(to) => (0, 0, 0),
+        None => (0, 0, 0),
+        Some(ChessMove::SimpleMove(from, to)) => (1, square(from), square(to)),
+        Some(ChessMove::EnPassant(from, to, _)) => {
+            (2, square(from), square(to))
+        }
+        Some(ChessMove::Castling(CastlingDirection::Kingside)) => (3, 0, 0),
+        Some(ChessMove::Castling(CastlingDirection::Queenside)) => (4, 0, 0),
+        Some(ChessMove::NullMove) => (5, 0, 0),
+    };
+
+    kind << (2 * SQUARE_BITS) | to << SQUARE_BITS | from
+}
+
+fn unpack_move(packed: u64) -> Option<ChessMove> {
+    let square = |index: u64| Square {
+        file: (index & u64::from(MAX_STORED_COORDINATE)) as u8,
+        rank: (index >> (SQUARE_BITS / 2) & u64::from(MAX_STORED_COORDINATE))
+            as u8,
+    };
+    let from = square(packed);
+    let to = square(packed >> SQUARE_BITS);
+
+    match packed >> (2 * SQUARE_BITS) {
+        1 => Some(ChessMove::SimpleMove(from, to)),
+        2 => {
+            let captured = Square {
+                file: to.file,
+                rank: from.rank,
+            };
+            Some(ChessMove::EnPassant(from, to, captured))
+        }
+        3 => Some(ChessMove::Castling(CastlingDirection::Kingside)),
+        4 => Some(ChessMove::Castling(CastlingDirection::Queenside)),
+        5 => Some(ChessMove::NullMove),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    #[test]
+    fn finds_stored_entry() {
+        let table = TranspositionTable::new(1);
+        let chess_move: ChessMove = "e2e4".try_into().unwrap();
+        table.store(
+            42,
+            3,
+            Bound::Exact,
+            Evaluation::Estimate(25),
+            Some(chess_move.clone()),
+        );
+
+        let entry = table.get(42).unwrap();
+        assert_eq!(entry.depth, 3);
+        assert_eq!(entry.bound, Bound::Exact);
+        assert_eq!(entry.evaluation, Evaluation::Estimate(25));
+        assert_eq!(entry.best_move, Some(chess_move));
+    }
+
+    #[test]
+    fn doesnt_find_other_positions() {
+        let table = TranspositionTable::new(1);
+        table.store(42, 3, Bound::Exact, Evaluation::Estimate(25), None);
+
+        assert!(table.get(43).is_none());
+    }
+
+    #[test]
+    fn keeps_deeper_entry_on_collision() {
+        let table = TranspositionTable::new(0);
+        table.store(1, 5, Bound::Exact, Evaluation::Estimate(1), None);
+        table.store(2, 2, Bound::Exact, Evaluation::Estimate(2), None);
+
+        assert!(table.get(1).is_some());
+        assert!(table.get(2).is_none());
+    }
+
+    #[test]
+    fn replaces_stale_entry_on_collision() {
+        let table = TranspositionTable::new(0);
+        table.store(1, 5, Bound::Exact, Evaluation::Estimate(1), None);
+        table.new_search();
+        table.store(2, 2, Bound::Exact, Evaluation::Estimate(2), None);
+
+        assert!(table.get(1).is_none());
+        assert!(table.get(2).is_some());
+    }
+
+    #[test]
+    fn keeps_best_move_when_updating_without_one() {
+        let table = TranspositionTable::new(1);
+        let chess_move: ChessMove = "e2e4".try_into().unwrap();
+        table.store(
+            42,
+            1,
+            Bound::Exact,
+            Evaluation::Estimate(0),
+            Some(chess_move.clone()),
+        );
+        table.store(42, 2, Bound::Upper, Evaluation::Estimate(-5), None);
+
+        assert_eq!(table.get(42).unwrap().best_move, Some(chess_move));
+    }
+
+    #[test]
+    fn clear_forgets_everything() {
+        let table = TranspositionTable::new(1);
+        table.store(42, 3, Bound::Exact, Evaluation::Estimate(25), None);
+        table.clear();
+
+        assert!(table.get(42).is_none());
+    }
+
+    #[test]
+    fn stores_every_kind_of_entry() {
+        let square = |file, rank| Square { file, rank };
+        let moves = [
+            None,
+            Some(ChessMove::SimpleMove(square(4, 1), square(4, 3))),
+            Some(ChessMove::EnPassant(
+                square(3, 4),
+                square(4, 5),
+                square(4, 4),
+            )),
+            Some(ChessMove::Castling(CastlingDirection::Kingside)),
+            Some(ChessMove::Castling(CastlingDirection::Queenside)),
+            Some(ChessMove::NullMove),
+        ];
+        let evaluations = [
+            Evaluation::Estimate(0),
+            Evaluation::Estimate(-1234),
+            Evaluation::Estimate(MAX_STORED_CENTIPAWNS),
+            Evaluation::Certain(ChessResult::Draw, 0),
+            Evaluation::Certain(ChessResult::Checkmate(Side::White), 7),
+            Evaluation::Certain(ChessResult::Checkmate(Side::Black), 255),
+        ];
+        let bounds = [Bound::Exact, Bound::Lower, Bound::Upper];
+
+        let table = TranspositionTable::new(1);
+        for best_move in &moves {
+            for evaluation in evaluations {
+                for bound in bounds {
+                    let key = u64::MAX - 7;
+                    table.store(key, 255, bound, evaluation, best_move.clone());
+
+                    let entry = table.get(key).unwrap();
+                    assert_eq!(entry.depth, 255);
+                    assert_eq!(entry.bound, bound);
+                    assert_eq!(entry.evaluation, evaluation);
+                    assert_eq!(&entry.best_move, best_move);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stores_moves_on_big_boards() {
+        let square = |file, rank| Square { file, rank };
+        let table = TranspositionTable::new(1);
+        let chess_move = ChessMove::SimpleMove(square(15, 3), square(2, 15));
+        table.store(
+            1,
+            1,
+            Bound::Exact,
+            Evaluation::Estimate(0),
+            Some(chess_move.clone()),
+        );
+
+        assert_eq!(table.get(1).unwrap().best_move, Some(chess_move));
+    }
+
+    #[test]
+    fn drops_moves_that_dont_fit() {
+        let square = |file, rank| Square { file, rank };
+        let table = TranspositionTable::new(1);
+        let chess_move = ChessMove::SimpleMove(square(0, 0), square(16, 0));
+        table.store(
+            1,
+            1,
+            Bound::Exact,
+            Evaluation::Estimate(0),
+            Some(chess_move),
+        );
+
+        let entry = table.get(1).unwrap();
+        assert_eq!(entry.evaluation, Evaluation::Estimate(0));
+        assert_eq!(entry.best_move, None);
+    }
+
+    #[test]
+    fn clamps_huge_estimates() {
+        let table = TranspositionTable::new(1);
+        table.store(1, 1, Bound::Exact, Evaluation::Estimate(i32::MIN), None);
+
+        assert_eq!(
+            table.get(1).unwrap().evaluation,
+            Evaluation::Estimate(-MAX_STORED_CENTIPAWNS)
+        );
+    }
+
+    #[test]
+    fn can_be_shared_between_threads() {
+        let table = TranspositionTable::new(0);
+
+        // Every thread keeps overwriting the same slot. Whatever is read back
+        // has to be an entry one of them actually stored.
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let table = &table;
+                scope.spawn(move || {
+                    for i in 0..10_000 {
+                        let key = thread * 1_000_000 + i;
+                        let evaluation = Evaluation::Estimate(key as i32);
+                        table.store(key, 1, Bound::Exact, evaluation, None);
+
+                        if let Some(entry) = table.get(key) {
+                            assert_eq!(entry.evaluation, evaluation);
+                        }
+                    }
+                });
+            }
+        });
+    }
+}